@@ -0,0 +1,293 @@
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use noise::Perlin;
+
+use island_procgen::generation::GenerationConfig;
+use island_procgen::generator::{Generator, GENERATORS};
+use island_procgen::macro_map::MacroMap;
+use island_procgen::terrain::{autotile_ground, sample_noise_grid};
+use island_procgen::*;
+
+fn gen_chunk_benchmark(c: &mut Criterion) {
+    ComputeTaskPool::get_or_init(TaskPool::default);
+
+    let config = GenerationConfig::default();
+    let macro_map = MacroMap::new(42, &config);
+    let mut group = c.benchmark_group("gen_chunk");
+    // v1 is the generator the hash set baseline was, only the storage differs
+    let gen_chunk_v1 = GENERATORS[0].gen_chunk;
+    group.bench_function("dense_parallel", |b| {
+        b.iter(|| {
+            gen_chunk_v1(
+                black_box(42),
+                black_box((4, 4)),
+                (CHUNK_W, CHUNK_H),
+                &config,
+                &macro_map,
+            )
+        })
+    });
+    group.bench_function("hashset_single_threaded", |b| {
+        b.iter(|| hashset_baseline::gen_chunk(black_box(42), black_box((4, 4))))
+    });
+
+    // The latest generator erodes its regions once per macro map, a fresh map pays for it
+    let gen_chunk = Generator::latest().gen_chunk;
+    group.bench_function("latest", |b| {
+        b.iter(|| {
+            gen_chunk(
+                black_box(42),
+                black_box((4, 4)),
+                (CHUNK_W, CHUNK_H),
+                &config,
+                &macro_map,
+            )
+        })
+    });
+    group.bench_function("latest_cold_erosion", |b| {
+        b.iter_batched(
+            || MacroMap::new(42, &config),
+            |macro_map| {
+                gen_chunk(
+                    black_box(42),
+                    black_box((4, 4)),
+                    (CHUNK_W, CHUNK_H),
+                    &config,
+                    &macro_map,
+                )
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn neighbourhood_benchmark(c: &mut Criterion) {
+    ComputeTaskPool::get_or_init(TaskPool::default);
+
+    let config = GenerationConfig::default();
+    let macro_map = MacroMap::new(42, &config);
+    let gen_chunk = Generator::latest().gen_chunk;
+    c.bench_function("gen_chunk_3x3", |b| {
+        b.iter(|| {
+            for x in 3..=5 {
+                for y in 3..=5 {
+                    black_box(gen_chunk(
+                        black_box(42),
+                        (x, y),
+                        (CHUNK_W, CHUNK_H),
+                        &config,
+                        &macro_map,
+                    ));
+                }
+            }
+        })
+    });
+}
+
+fn autotile_benchmark(c: &mut Criterion) {
+    ComputeTaskPool::get_or_init(TaskPool::default);
+
+    let start = (4 * CHUNK_W as i32, 4 * CHUNK_H as i32);
+    let noise = Perlin::new(42);
+    let config = GenerationConfig::default();
+    let samples = sample_noise_grid(
+        &noise,
+        &config,
+        (start.0 - 1, start.1 - 1),
+        CHUNK_W + 2,
+        CHUNK_H + 2,
+    );
+    let ground_map = samples.map(|(noise_val, _)| noise_val > 0.0);
+
+    c.bench_function("autotile_ground", |b| {
+        b.iter(|| autotile_ground(black_box(&ground_map), start, true))
+    });
+}
+
+/// The hash set based generator dense storage replaced, copied unchanged from before
+/// the switch as a baseline
+#[allow(dead_code, clippy::collapsible_else_if)]
+mod hashset_baseline {
+    use bevy::utils::HashSet;
+    use noise::{NoiseFn, Perlin};
+    use rand::Rng;
+
+    use island_procgen::*;
+
+    #[derive(Clone, Copy, Eq, PartialEq, Hash)]
+    pub struct Tile {
+        pub pos: (i32, i32),
+        pub sprite: usize,
+        pub z_index: i32,
+    }
+
+    /// Everything generated for a single chunk, ready to be spawned
+    pub struct ChunkData {
+        pub pos: (i32, i32),
+        pub tiles: Vec<Tile>,
+        pub ground: HashSet<(i32, i32)>,
+    }
+
+    pub(crate) fn gen_chunk(gen_seed: u32, pos: (i32, i32)) -> ChunkData {
+        let mut rng = rand::thread_rng();
+        let noise = Perlin::new(gen_seed);
+
+        let mut tiles = Vec::new();
+        let mut ground_map = HashSet::new();
+        let start = (pos.0 * CHUNK_W as i32, pos.1 * CHUNK_H as i32);
+        let end = (start.0 + CHUNK_W as i32, start.1 + CHUNK_H as i32);
+        let in_chunk = |x: i32, y: i32| x >= start.0 && x < end.0 && y >= start.1 && y < end.1;
+        for x in start.0 - 1..end.0 + 1 {
+            for y in start.1 - 1..end.1 + 1 {
+                let (noise_val, [_, _, noise_val3, noise_val4]) = sample_noise(&noise, x, y);
+                let chance = rng.gen_range(0.0..1.0);
+
+                // Ground
+                if noise_val > 0.0 {
+                    ground_map.insert((x, y));
+                } else {
+                    continue;
+                }
+
+                // The border is only sampled for autotiling,
+                // its decorations belong to the neighbouring chunk
+                if !in_chunk(x, y) {
+                    continue;
+                }
+
+                // Too close to shore
+                if noise_val < 0.05 {
+                    continue;
+                }
+
+                // Dense Forest
+                if (noise_val > 0.5 || noise_val3 > 0.98) && chance > 0.2 {
+                    tiles.push(Tile::new((x, y), 27, 5));
+                    continue;
+                }
+                // Patch Forest
+                if noise_val3 > 0.5 && noise_val < 0.5 && chance > 0.4 {
+                    let chance2 = rng.gen_range(0.0..1.0);
+                    let tile = if chance2 > 0.7 {
+                        rng.gen_range(24..=26)
+                    } else {
+                        rng.gen_range(24..=25)
+                    };
+                    tiles.push(Tile::new((x, y), tile, 3));
+                    continue;
+                }
+                // Sparse Forest
+                if noise_val4 > 0.4 && noise_val < 0.5 && noise_val3 < 0.5 && chance > 0.9 {
+                    let chance = rng.gen_range(0.0..1.0);
+                    let tile = if chance > 0.78 {
+                        rng.gen_range(28..=29)
+                    } else {
+                        rng.gen_range(24..=25)
+                    };
+                    tiles.push(Tile::new((x, y), tile, 3));
+                    continue;
+                }
+
+                // Bones
+                if noise_val > 0.3 && noise_val < 0.5 && noise_val3 < 0.5 && chance > 0.98 {
+                    let tile = rng.gen_range(40..=43);
+                    tiles.push(Tile::new((x, y), tile, 1));
+                    continue;
+                }
+
+                // Settlements
+                if noise_val > 0.1 && noise_val < 0.3 && noise_val3 < 0.4 && chance > 0.8 {
+                    let chance2 = rng.gen_range(0.0..1.0);
+
+                    if chance2 > 0.98 {
+                        let chance3 = rng.gen_range(0.0..1.0);
+                        let tile = if chance3 > 0.75 {
+                            rng.gen_range(18..=19)
+                        } else {
+                            rng.gen_range(16..=17)
+                        };
+                        tiles.push(Tile::new((x, y), tile, 8));
+                    } else {
+                        if noise_val > 0.2 && noise_val < 0.3 && noise_val3 < 0.3 && chance > 0.9 {
+                            tiles.push(Tile::new((x, y), 32, 1));
+                        }
+                    }
+
+                    continue;
+                }
+
+                // Color Check
+                // if noise_val > 0.1 && noise_val4 < 0.5 {
+                //     tiles.push(Tile::new((x, y), 64, 1));
+                //     continue;
+                // }
+            }
+        }
+
+        let mut ground = HashSet::new();
+        for (x, y) in ground_map.iter() {
+            if !in_chunk(*x, *y) {
+                continue;
+            }
+
+            // Ignore edges
+            // This will help in better player visualization when going from land to water
+            let (num_nei, tile) = process_tile((*x, *y), &ground_map);
+            if num_nei == 1 {
+                continue;
+            }
+
+            ground.insert((*x, *y));
+            tiles.push(Tile::new((*x, *y), tile, 0));
+        }
+
+        ChunkData { pos, tiles, ground }
+    }
+
+    /// Averaged elevation at a grid position along with the individual octaves
+    pub fn sample_noise(noise: &Perlin, x: i32, y: i32) -> (f64, [f64; 4]) {
+        let octaves = [100.5, 53.5, 43.5, 23.5].map(|s| noise.get([x as f64 / s, y as f64 / s]));
+        (octaves.iter().sum::<f64>() / 4.0, octaves)
+    }
+
+    fn process_tile((x, y): (i32, i32), occupied: &HashSet<(i32, i32)>) -> (i32, usize) {
+        let nei_options = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        let mut nei = [1, 1, 1, 1];
+        let mut nei_count = 4;
+        for (idx, (i, j)) in nei_options.iter().enumerate() {
+            if !occupied.contains(&(x + i, y + j)) {
+                nei[idx] = 0;
+                nei_count -= 1;
+            }
+        }
+
+        let tile = match nei {
+            [0, 1, 1, 0] => 3,
+            [1, 0, 1, 0] => 4,
+            [0, 1, 0, 1] => 1,
+            [1, 0, 0, 1] => 2,
+            _ => 0,
+        };
+
+        (nei_count, tile)
+    }
+
+    impl Tile {
+        fn new(pos: (i32, i32), sprite: usize, z_index: i32) -> Self {
+            Self {
+                pos,
+                sprite,
+                z_index,
+            }
+        }
+    }
+}
+
+criterion_group!(
+    benches,
+    gen_chunk_benchmark,
+    neighbourhood_benchmark,
+    autotile_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::diagnostic::DiagnosticsPlugin;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use island_procgen::generation::GenerationPlugin;
+use island_procgen::palette::PalettePlugin;
+use island_procgen::player::{CurrentPlayerChunkPos, PlayerVelocity};
+use island_procgen::terrain::{
+    ChunkSpawnedEvent, ChunkUnloadedEvent, RequiredChunks, TerrainPlugin,
+};
+use island_procgen::tileset::TilesetPlugin;
+
+const WALK_CHUNKS: i32 = 8;
+const MAX_UPDATES_PER_CHUNK: usize = 10_000;
+
+#[derive(Resource, Default)]
+struct LoadedChunks(HashSet<(i32, i32)>);
+
+/// Terrain streaming without a window or renderer, the player is moved by hand
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.build().disable::<ScheduleRunnerPlugin>())
+        .add_plugins(AssetPlugin::default())
+        .add_plugins(DiagnosticsPlugin)
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlas>()
+        .insert_resource(CurrentPlayerChunkPos::default())
+        .insert_resource(PlayerVelocity::default())
+        .insert_resource(LoadedChunks::default())
+        .add_plugins(TilesetPlugin)
+        .add_plugins(PalettePlugin)
+        .add_plugins(GenerationPlugin)
+        .add_plugins(TerrainPlugin::default())
+        .add_systems(Last, track_loaded_chunks);
+    app
+}
+
+fn track_loaded_chunks(
+    mut loaded: ResMut<LoadedChunks>,
+    mut ev_spawned: EventReader<ChunkSpawnedEvent>,
+    mut ev_unloaded: EventReader<ChunkUnloadedEvent>,
+) {
+    for ev in ev_unloaded.read() {
+        loaded.0.remove(&ev.pos);
+    }
+    for ev in ev_spawned.read() {
+        loaded.0.insert(ev.pos);
+    }
+}
+
+/// Walks east one chunk at a time, waiting for the whole neighbourhood to spawn at each step
+fn walk(app: &mut App, chunks: i32) {
+    for x in 0..chunks {
+        app.world.resource_mut::<CurrentPlayerChunkPos>().0 = (x, 0);
+        for _ in 0..MAX_UPDATES_PER_CHUNK {
+            app.update();
+
+            let required = &app.world.resource::<RequiredChunks>().0;
+            let loaded = &app.world.resource::<LoadedChunks>().0;
+            if required.is_subset(loaded) {
+                break;
+            }
+        }
+    }
+}
+
+fn streaming_benchmark(c: &mut Criterion) {
+    c.bench_function("walk_8_chunks", |b| {
+        b.iter_batched(
+            headless_app,
+            |mut app| walk(&mut app, WALK_CHUNKS),
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = streaming_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,130 @@
+use std::mem::size_of;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::names::Place;
+use crate::terrain::{ChunkData, Tile};
+
+/// LRU cache of generated chunks, bounded by an approximate memory budget
+///
+/// Sits between generation and spawning so a chunk is only generated once
+/// per session, revisiting an area just re-spawns the cached tiles
+#[derive(Resource)]
+pub struct ChunkCache {
+    chunks: HashMap<(i32, i32), CacheEntry>,
+    budget_bytes: usize,
+    used_bytes: usize,
+    tick: u64,
+}
+
+struct CacheEntry {
+    data: Arc<ChunkData>,
+    last_used: u64,
+}
+
+impl ChunkCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            budget_bytes,
+            used_bytes: 0,
+            tick: 0,
+        }
+    }
+
+    pub fn get(&mut self, pos: (i32, i32)) -> Option<Arc<ChunkData>> {
+        self.tick += 1;
+        let entry = self.chunks.get_mut(&pos)?;
+        entry.last_used = self.tick;
+        Some(entry.data.clone())
+    }
+
+    pub fn insert(&mut self, data: ChunkData) -> Arc<ChunkData> {
+        self.tick += 1;
+        let pos = data.pos;
+        let data = Arc::new(data);
+        if let Some(old) = self.chunks.insert(
+            pos,
+            CacheEntry {
+                data: data.clone(),
+                last_used: self.tick,
+            },
+        ) {
+            self.used_bytes -= old.data.size_bytes();
+        }
+
+        self.used_bytes += data.size_bytes();
+        self.evict(pos);
+        data
+    }
+
+    pub fn get_or_insert_with(
+        &mut self,
+        pos: (i32, i32),
+        gen: impl FnOnce() -> ChunkData,
+    ) -> Arc<ChunkData> {
+        match self.get(pos) {
+            Some(data) => data,
+            None => self.insert(gen()),
+        }
+    }
+
+    /// Checks for a chunk without counting it as a use
+    pub fn contains(&self, pos: (i32, i32)) -> bool {
+        self.chunks.contains_key(&pos)
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.used_bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    /// Drops the least recently used chunks until the cache fits the budget,
+    /// `keep` is never evicted so a chunk larger than the budget still gets spawned
+    fn evict(&mut self, keep: (i32, i32)) {
+        while self.used_bytes > self.budget_bytes {
+            let oldest = self
+                .chunks
+                .iter()
+                .filter(|(pos, _)| **pos != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(pos, _)| *pos);
+            let Some(pos) = oldest else {
+                return;
+            };
+
+            if let Some(entry) = self.chunks.remove(&pos) {
+                self.used_bytes -= entry.data.size_bytes();
+            }
+        }
+    }
+}
+
+impl ChunkData {
+    /// Rough heap footprint
+    pub fn size_bytes(&self) -> usize {
+        size_of::<Self>()
+            + self.tiles.capacity() * size_of::<Tile>()
+            + self.ground.size_bytes()
+            + self.biomes.size_bytes()
+            + self
+                .places
+                .iter()
+                .map(|place| size_of::<Place>() + place.name.capacity())
+                .sum::<usize>()
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use bevy::diagnostic::Diagnostics;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy::utils::{HashMap, HashSet, Instant};
+
+use crate::chunk_cache::ChunkCache;
+use crate::diagnostics::TerrainDiagnosticsPlugin;
+use crate::generation::ActiveGenerationConfig;
+use crate::generator::{Generator, WorldCode};
+use crate::macro_map::ActiveMacroMap;
+use crate::player::{CurrentPlayerChunkPos, Player, PlayerVelocity};
+use crate::settings::WorldSettings;
+use crate::terrain::{
+    ChunkData, ChunkGeneratedEvent, CurrentChunks, GenerationSeed, GeneratorVersion, RequiredChunks,
+};
+use crate::utils::*;
+
+/// Chunks the player is heading towards, generated ahead of time
+#[derive(Resource, Default)]
+pub struct PrefetchChunks(pub HashSet<(i32, i32)>);
+
+/// Generated chunk along with how long it took
+type GenResult = (ChunkData, Duration);
+
+/// Chunk generation running on the async compute pool, tagged with the world it was started for
+#[derive(Resource, Default)]
+pub struct ChunkGenTasks(pub(crate) HashMap<(i32, i32), (WorldCode, Task<GenResult>)>);
+
+pub(crate) fn prefetch_chunks(
+    settings: Res<WorldSettings>,
+    player_velocity: Res<PlayerVelocity>,
+    player_query: Query<&Transform, With<Player>>,
+    mut prefetch: ResMut<PrefetchChunks>,
+) {
+    let mut chunks = HashSet::new();
+    let velocity = player_velocity.0;
+
+    if let Ok(transform) = player_query.get_single() {
+        if velocity != Vec2::ZERO {
+            // Sample along the predicted path, sprinting looks further ahead
+            let pos = transform.translation.truncate();
+            let steps = settings.prefetch_steps;
+            for step in 1..=steps {
+                let t = settings.prefetch_lookahead_secs * step as f32 / steps as f32;
+                let p = pos + velocity * t;
+                let (x, y) = world_to_terrain_chunk(p.x, p.y, settings.chunk_size);
+                for i in -1..=1 {
+                    for j in -1..=1 {
+                        chunks.insert((x + i, y + j));
+                    }
+                }
+            }
+        }
+    }
+
+    if chunks != prefetch.0 {
+        prefetch.0 = chunks;
+    }
+}
+
+pub(crate) fn queue_chunk_generation(
+    settings: Res<WorldSettings>,
+    gen_config: Res<ActiveGenerationConfig>,
+    macro_map: Res<ActiveMacroMap>,
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    player_pos: Res<CurrentPlayerChunkPos>,
+    required: Res<RequiredChunks>,
+    prefetch: Res<PrefetchChunks>,
+    current_chunks: Res<CurrentChunks>,
+    chunk_cache: Res<ChunkCache>,
+    mut tasks: ResMut<ChunkGenTasks>,
+) {
+    let free = settings.max_chunk_gen_tasks.saturating_sub(tasks.0.len());
+    if free == 0 {
+        return;
+    }
+
+    // Required chunks go first, then the nearest missing chunk
+    let (px, py) = player_pos.0;
+    let mut missing = required
+        .0
+        .iter()
+        .map(|pos| (false, *pos))
+        .chain(prefetch.0.difference(&required.0).map(|pos| (true, *pos)))
+        .filter(|(_, pos)| {
+            current_chunks.0.get(pos).is_none_or(|chunk| chunk.stale)
+                && !chunk_cache.contains(*pos)
+                && !tasks.0.contains_key(pos)
+        })
+        .collect::<Vec<_>>();
+    missing.sort_by_key(|(is_prefetch, (x, y))| (*is_prefetch, x.abs_diff(px).max(y.abs_diff(py))));
+
+    let world = WorldCode {
+        version: version.0,
+        seed: seed.0,
+        config: Some(gen_config.digest),
+    };
+    let gen_chunk = Generator::get(version.0)
+        .unwrap_or_else(Generator::latest)
+        .gen_chunk;
+    let pool = AsyncComputeTaskPool::get();
+    for (_, pos) in missing.into_iter().take(free) {
+        let chunk_size = settings.chunk_size;
+        let config = gen_config.config.clone();
+        let macro_map = macro_map.0.clone();
+        let task = pool.spawn(async move {
+            let started_at = Instant::now();
+            let data = gen_chunk(world.seed, pos, chunk_size, &config, &macro_map);
+            (data, started_at.elapsed())
+        });
+        tasks.0.insert(pos, (world, task));
+    }
+}
+
+pub(crate) fn poll_chunk_generation(
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    mut tasks: ResMut<ChunkGenTasks>,
+    mut chunk_cache: ResMut<ChunkCache>,
+    mut ev_generated: EventWriter<ChunkGeneratedEvent>,
+    mut diagnostics: Diagnostics,
+) {
+    let finished = tasks
+        .0
+        .iter()
+        .filter(|(_, (_, task))| task.is_finished())
+        .map(|(pos, _)| *pos)
+        .collect::<Vec<_>>();
+
+    for pos in finished {
+        let Some((world, task)) = tasks.0.remove(&pos) else {
+            continue;
+        };
+
+        // Chunks of an old world are dropped
+        let (data, gen_time) = block_on(task);
+        diagnostics.add_measurement(TerrainDiagnosticsPlugin::CHUNK_GEN_TIME, || {
+            gen_time.as_secs_f64() * 1000.0
+        });
+        if world.seed == seed.0 && world.version == version.0 {
+            let data = chunk_cache.insert(data);
+            ev_generated.send(ChunkGeneratedEvent { pos, data });
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::mem::size_of;
+
+/// Dense `w`×`h` layer of a chunk in terrain grid coords, `origin` is the top left cell
+#[derive(Clone)]
+pub struct ChunkGrid<T> {
+    origin: (i32, i32),
+    w: usize,
+    h: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Copy> ChunkGrid<T> {
+    pub fn new(origin: (i32, i32), w: usize, h: usize, value: T) -> Self {
+        Self {
+            origin,
+            w,
+            h,
+            cells: vec![value; w * h],
+        }
+    }
+
+    /// Builds a grid from cells laid out row by row
+    pub fn from_cells(origin: (i32, i32), w: usize, h: usize, cells: Vec<T>) -> Self {
+        assert_eq!(cells.len(), w * h, "cell count doesn't match the grid size");
+        Self {
+            origin,
+            w,
+            h,
+            cells,
+        }
+    }
+
+    pub fn origin(&self) -> (i32, i32) {
+        self.origin
+    }
+
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    pub fn contains(&self, pos: (i32, i32)) -> bool {
+        self.index(pos).is_some()
+    }
+
+    pub fn get(&self, pos: (i32, i32)) -> Option<T> {
+        self.index(pos).map(|idx| self.cells[idx])
+    }
+
+    pub fn set(&mut self, pos: (i32, i32), value: T) {
+        if let Some(idx) = self.index(pos) {
+            self.cells[idx] = value;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), T)> + '_ {
+        self.cells.iter().enumerate().map(|(idx, value)| {
+            let (i, j) = (idx % self.w, idx / self.w);
+            ((self.origin.0 + i as i32, self.origin.1 + j as i32), *value)
+        })
+    }
+
+    pub fn map<U: Copy>(&self, f: impl Fn(T) -> U) -> ChunkGrid<U> {
+        ChunkGrid {
+            origin: self.origin,
+            w: self.w,
+            h: self.h,
+            cells: self.cells.iter().map(|value| f(*value)).collect(),
+        }
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        size_of::<Self>() + self.cells.capacity() * size_of::<T>()
+    }
+
+    fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        let (i, j) = (x - self.origin.0, y - self.origin.1);
+        if i < 0 || j < 0 || i as usize >= self.w || j as usize >= self.h {
+            return None;
+        }
+
+        Some(j as usize * self.w + i as usize)
+    }
+}
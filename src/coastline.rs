@@ -0,0 +1,87 @@
+use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+
+use crate::chunk_grid::ChunkGrid;
+use crate::generation::GenerationConfig;
+
+/// Domain warp, sample positions are pushed around by noise before the terrain is sampled
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct WarpLayer {
+    /// Tiles per noise period
+    pub scale: f64,
+    /// Largest push in tiles
+    pub strength: f64,
+}
+
+// Warp and roughness read the tile noise far away from where the terrain samples it,
+// so they don't follow the terrain
+const WARP_OFFSET: f64 = 1013.37;
+const ROUGHNESS_OFFSET: f64 = -2741.61;
+
+/// Applies every warp layer in order, each one warping the result of the last
+pub fn warp(noise: &Perlin, layers: &[WarpLayer], x: f64, y: f64) -> (f64, f64) {
+    layers
+        .iter()
+        .enumerate()
+        .fold((x, y), |(x, y), (i, layer)| {
+            let offset = WARP_OFFSET * (i + 1) as f64;
+            let (sx, sy) = (x / layer.scale, y / layer.scale);
+            let dx = noise.get([sx + offset, sy]);
+            let dy = noise.get([sx, sy + offset]);
+            (x + dx * layer.strength, y + dy * layer.strength)
+        })
+}
+
+/// Small scale noise added to elevations near sea level, fading out inland and offshore
+/// so biome thresholds further from the coast aren't affected
+pub fn coast_roughness(
+    config: &GenerationConfig,
+    noise: &Perlin,
+    x: i32,
+    y: i32,
+    elevation: f64,
+) -> f64 {
+    let coast = 1.0 - (elevation - config.sea_level).abs() / config.coast_band;
+    if coast <= 0.0 {
+        return 0.0;
+    }
+
+    let s = config.coast_roughness_scale;
+    let value = noise.get([
+        x as f64 / s + ROUGHNESS_OFFSET,
+        y as f64 / s + ROUGHNESS_OFFSET,
+    ]);
+    value * config.coast_roughness * coast
+}
+
+/// Cellular automata over a land mask, land with five or more land neighbours stays land,
+/// with three or fewer it turns to water, otherwise it's kept. Removes single tile specks,
+/// holes and one tile wide strips. Every pass drops the outer ring of the grid,
+/// the border needs to be as wide as the number of passes for chunks to tile
+pub fn smooth_coastline(land: &ChunkGrid<bool>, passes: usize) -> ChunkGrid<bool> {
+    let mut land = land.clone();
+    for _ in 0..passes {
+        let origin = land.origin();
+        let (w, h) = (land.width() - 2, land.height() - 2);
+        let mut cells = Vec::with_capacity(w * h);
+        for j in 0..h as i32 {
+            for i in 0..w as i32 {
+                let (x, y) = (origin.0 + 1 + i, origin.1 + 1 + j);
+                let neighbours = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(|&(dx, dy)| {
+                        (dx, dy) != (0, 0) && land.get((x + dx, y + dy)) == Some(true)
+                    })
+                    .count();
+                cells.push(match neighbours {
+                    5.. => true,
+                    0..=3 => false,
+                    _ => land.get((x, y)) == Some(true),
+                });
+            }
+        }
+        land = ChunkGrid::from_cells((origin.0 + 1, origin.1 + 1), w, h, cells);
+    }
+
+    land
+}
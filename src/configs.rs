@@ -1,13 +1,33 @@
+// Defaults of `WorldSettings` and `PlayerSettings` are the consts below,
+// values in the settings file override them at startup
+pub const SETTINGS_PATH: &str = "settings.ron";
+/// Bookmarked world codes, written next to the settings file
+pub const BOOKMARKS_PATH: &str = "bookmarks.ron";
+
 // Sprites
 pub const TILE_W: usize = 6;
 pub const TILE_H: usize = 8;
-pub const SPRITE_SHEET_W: usize = 8;
-pub const SPRITE_SHEET_H: usize = 9;
 pub const SPRITE_SCALE_FACTOR: usize = 5;
-pub const PLAYER_SPRITE_INDEX: usize = 56;
-pub const SPRITE_SHEET_PATH: &str = "sprite-sheet.png";
-pub const SPRITE_PADDING: f32 = 2.0;
-pub const SPRITE_SHEET_OFFSET: f32 = 2.0;
+pub const TILESET_PATH: &str = "tilesets/default.tileset.ron";
+pub const GENERATION_CONFIG_PATH: &str = "generation/default.generation.ron";
+/// Bumped whenever the same seed would generate a different world, part of world codes
+pub const GENERATOR_VERSION: u8 = 4;
+/// Bits of the generation config digest kept in world codes
+pub const CONFIG_DIGEST_MASK: u16 = 0x0fff;
+/// Macro map cells sampled up front around the origin, in each direction
+pub const MACRO_MAP_RADIUS: i32 = 64;
+/// Eroded regions kept around per world, each one is shared by the chunks it overlaps
+pub const EROSION_CACHE_REGIONS: usize = 64;
+pub const TILE_ANIMATION_INTERVAL: f32 = 0.8;
+/// Share of open water tiles with an animated shimmer
+pub const WATER_SHIMMER_CHANCE: f64 = 0.008;
+/// Share of houses with smoke rising from the chimney
+pub const CHIMNEY_SMOKE_CHANCE: f64 = 0.5;
+pub const PALETTE_PATHS: &[&str] = &[
+    "palettes/default.palette.ron",
+    "palettes/autumn.palette.ron",
+    "palettes/dusk.palette.ron",
+];
 
 // Window
 pub const GRID_COLS: usize = 1000;
@@ -16,11 +36,19 @@ pub const GRID_W: usize = GRID_COLS * TILE_W;
 pub const GRID_H: usize = GRID_ROWS * TILE_H;
 pub const WW: usize = 1920;
 pub const WH: usize = 1080;
-pub const BG_COLOR: (u8, u8, u8) = (181, 212, 220);
+/// Background color over the palette's water color, `None` uses the palette
+pub const BG_COLOR: Option<(u8, u8, u8)> = None;
 
 // Chunk
 pub const CHUNK_W: usize = 120;
 pub const CHUNK_H: usize = 100;
+pub const CHUNK_CACHE_BUDGET_MB: usize = 64;
+pub const MAX_CAMERA_CHUNK_SPAN: i32 = 6;
+pub const MAX_CHUNK_GEN_TASKS: usize = 4;
+pub const TILE_SPAWN_BUDGET: usize = 6000;
+pub const TILE_SPAWN_TIME_BUDGET_MS: f32 = 4.0;
+pub const PREFETCH_LOOKAHEAD_SECS: f32 = 15.0;
+pub const PREFETCH_STEPS: usize = 6;
 
 // Player
 pub const PLAYER_SPEED: f32 = 1.0;
@@ -29,3 +57,31 @@ pub const PLAYER_ANIMATION_INTERVAL: f32 = 0.3;
 pub const WALK_TRAIL_TIMER: f32 = 1.2;
 pub const TRAIL_LIFE_SPAN: f32 = 5.0;
 pub const PLAYER_JUMP_TIME: f32 = 0.3;
+/// World position the spawn point search starts from
+pub const PLAYER_SPAWN_POINT: (f32, f32) = (0.0, 0.0);
+pub const SPAWN_MIN_ISLAND_TILES: usize = 400;
+/// Chunk rings searched around the spawn point for land, every ring generates its chunks
+/// before the player can move, 3 rings are at most 49 chunks
+pub const SPAWN_SEARCH_RADIUS: i32 = 3;
+
+// Names
+/// Chunks each way a sea spans, every sea region gets its own name
+pub const SEA_REGION_CHUNKS: i32 = 4;
+/// The player is in a village within this many tiles of its center
+pub const VILLAGE_RADIUS: u32 = 12;
+/// Houses in the same cell of this many tiles each way are one village
+pub const VILLAGE_CELL_TILES: i32 = 24;
+/// Smaller islands are left unnamed
+pub const NAMED_ISLAND_MIN_TILES: usize = 200;
+pub const PLACE_BANNER_TIME: f32 = 3.0;
+
+// Level of detail
+pub const LOD_SPRITE_MAX_SCALE: f32 = 2.0;
+pub const LOD_COARSE_MIN_SCALE: f32 = 8.0;
+pub const LOD_TILE_RADIUS: u32 = 4;
+pub const LOD_COARSE_TILES: usize = 4;
+pub const MAX_LOD_CHUNK_SPAN: i32 = 16;
+pub const LOD_CHUNKS_PER_FRAME: usize = 4;
+pub const LOD_GROUND_COLOR: (u8, u8, u8) = (232, 214, 172);
+pub const LOD_FOREST_COLOR: (u8, u8, u8) = (118, 160, 96);
+pub const LOD_DENSE_FOREST_COLOR: (u8, u8, u8) = (72, 118, 76);
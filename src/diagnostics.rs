@@ -0,0 +1,84 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+
+use crate::chunk_cache::ChunkCache;
+use crate::terrain::{CurrentChunks, GroundTiles};
+
+/// Publishes terrain streaming stats to the diagnostics store,
+/// `LogDiagnosticsPlugin` prints them along with the frame time
+pub struct TerrainDiagnosticsPlugin;
+
+impl Plugin for TerrainDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(
+            Diagnostic::new(Self::CHUNK_GEN_TIME, "chunk_gen_time", 20).with_suffix("ms"),
+        )
+        .register_diagnostic(Diagnostic::new(
+            Self::TILES_SPAWNED,
+            "tiles_spawned_per_frame",
+            20,
+        ))
+        .register_diagnostic(Diagnostic::new(
+            Self::SPAWN_QUEUE_CHUNKS,
+            "spawn_queue_chunks",
+            20,
+        ))
+        .register_diagnostic(Diagnostic::new(
+            Self::SPAWN_QUEUE_TILES,
+            "spawn_queue_tiles",
+            20,
+        ))
+        .register_diagnostic(
+            Diagnostic::new(Self::LOADED_CHUNKS, "loaded_chunks", 1).with_smoothing_factor(0.0),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::CACHED_CHUNKS, "cached_chunks", 1).with_smoothing_factor(0.0),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::TILE_ENTITIES, "tile_entities", 1).with_smoothing_factor(0.0),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::GROUND_TILES, "ground_tiles", 1).with_smoothing_factor(0.0),
+        )
+        .add_systems(Update, Self::diagnostic_system);
+    }
+}
+
+impl TerrainDiagnosticsPlugin {
+    pub const CHUNK_GEN_TIME: DiagnosticId =
+        DiagnosticId::from_u128(227125747134216544238280397411070914913);
+    pub const TILES_SPAWNED: DiagnosticId =
+        DiagnosticId::from_u128(291968764910930313552993918686099237474);
+    pub const SPAWN_QUEUE_CHUNKS: DiagnosticId =
+        DiagnosticId::from_u128(215076182470148603571555326015687054867);
+    pub const SPAWN_QUEUE_TILES: DiagnosticId =
+        DiagnosticId::from_u128(72702648528933029261340857003783270606);
+    pub const LOADED_CHUNKS: DiagnosticId =
+        DiagnosticId::from_u128(248556975204210167150170686131398289505);
+    pub const CACHED_CHUNKS: DiagnosticId =
+        DiagnosticId::from_u128(31609402779949804440856913185191705231);
+    pub const TILE_ENTITIES: DiagnosticId =
+        DiagnosticId::from_u128(215300919165519464531757047216556880047);
+    pub const GROUND_TILES: DiagnosticId =
+        DiagnosticId::from_u128(304084849314717700729060937848827269594);
+
+    /// Counts that are cheap to read off the resources every frame,
+    /// generation time and spawn queue stats are measured where the work happens
+    fn diagnostic_system(
+        mut diagnostics: Diagnostics,
+        current_chunks: Res<CurrentChunks>,
+        chunk_cache: Res<ChunkCache>,
+        ground_tiles: Res<GroundTiles>,
+    ) {
+        diagnostics.add_measurement(Self::LOADED_CHUNKS, || current_chunks.0.len() as f64);
+        diagnostics.add_measurement(Self::CACHED_CHUNKS, || chunk_cache.len() as f64);
+        diagnostics.add_measurement(Self::TILE_ENTITIES, || {
+            current_chunks
+                .0
+                .values()
+                .map(|chunk| chunk.entities.len())
+                .sum::<usize>() as f64
+        });
+        diagnostics.add_measurement(Self::GROUND_TILES, || ground_tiles.0.len() as f64);
+    }
+}
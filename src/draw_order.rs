@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::settings::WorldSettings;
+use crate::utils::*;
+use crate::*;
+
+/// Keeps y-sorted entities layered by their position,
+/// anything lower on screen is drawn in front
+pub struct DrawOrderPlugin;
+
+/// Every sprite belongs to a layer, later layers are drawn on top.
+/// Only `Objects` is y-sorted, the other layers are flat
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum DrawLayer {
+    /// Far away terrain textures
+    Lod,
+    Ground,
+    /// Flat details lying on the ground, bones, walk trails
+    GroundDetail,
+    /// Trees, houses, the player
+    Objects,
+    /// Labels and debug overlays
+    Overlay,
+}
+
+/// Moves the entity within its layer whenever its transform changes
+/// or the sort origin moves
+#[derive(Component)]
+pub struct YSorted(pub DrawLayer);
+
+/// World y the sorted layers are spread around, it follows the camera a chunk row at a time
+/// so sorting keeps working however far from the world origin the camera goes
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct YSortOrigin {
+    pub y: f32,
+    /// World height sorted around `y`, objects further away share the ends of their layer
+    pub extent: f32,
+}
+
+impl Plugin for DrawOrderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<YSortOrigin>().add_systems(
+            PostUpdate,
+            (update_y_sort_origin, y_sort)
+                .chain()
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+impl FromWorld for YSortOrigin {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.resource::<WorldSettings>();
+        Self::around(0.0, settings.chunk_size, settings.max_camera_chunk_span)
+    }
+}
+
+impl DrawLayer {
+    /// Z range of the layer, the default 2d camera sees `0.0..1000.0`
+    fn range(self) -> (f32, f32) {
+        match self {
+            DrawLayer::Lod => (0.0, 1.0),
+            DrawLayer::Ground => (1.0, 2.0),
+            DrawLayer::GroundDetail => (2.0, 3.0),
+            DrawLayer::Objects => (10.0, 900.0),
+            DrawLayer::Overlay => (950.0, 951.0),
+        }
+    }
+
+    pub fn is_y_sorted(self) -> bool {
+        self == DrawLayer::Objects
+    }
+
+    /// Z of a flat layer, y-sorted layers start at the bottom of their range
+    /// until `YSorted` moves them into place
+    pub fn z(self) -> f32 {
+        self.range().0
+    }
+}
+
+impl YSortOrigin {
+    /// Origin for a camera at world `y`, the extent covers the chunk rows
+    /// that are loaded around the camera
+    pub fn around(y: f32, chunk_size: (usize, usize), chunk_span: i32) -> Self {
+        let (_, cy) = world_to_terrain_chunk(0.0, y, chunk_size);
+        let (_, y) = terrain_chunk_center(0, cy, chunk_size);
+        let row = (chunk_size.1 * TILE_H * SPRITE_SCALE_FACTOR) as f32;
+        Self {
+            y,
+            extent: row * (2 * chunk_span + 3) as f32,
+        }
+    }
+
+    /// Z of a sprite of `layer` standing at world `y`
+    pub fn z(&self, layer: DrawLayer, y: f32) -> f32 {
+        let (start, end) = layer.range();
+        if !layer.is_y_sorted() {
+            return start;
+        }
+
+        // Linear over the extent, lower on screen is closer to the camera
+        let t = (0.5 - (y - self.y) / self.extent).clamp(0.0, 1.0);
+        start + t * (end - start)
+    }
+}
+
+fn update_y_sort_origin(
+    settings: Res<WorldSettings>,
+    cam_query: Query<(&Camera, &GlobalTransform)>,
+    mut origin: ResMut<YSortOrigin>,
+) {
+    let Some((_, transform)) = cam_query.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+
+    let around = YSortOrigin::around(
+        transform.translation().y,
+        settings.chunk_size,
+        settings.max_camera_chunk_span,
+    );
+    if *origin != around {
+        *origin = around;
+    }
+}
+
+type SortedQuery<'w, 's, F = ()> = Query<'w, 's, (&'static YSorted, &'static mut Transform), F>;
+
+/// Every sorted entity moves when the origin does, otherwise only the ones that moved
+fn y_sort(
+    origin: Res<YSortOrigin>,
+    mut queries: ParamSet<(SortedQuery, SortedQuery<Changed<Transform>>)>,
+) {
+    let sort = |(sorted, mut transform): (&YSorted, Mut<Transform>)| {
+        let z = origin.z(sorted.0, transform.translation.y);
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    };
+    if origin.is_changed() {
+        queries.p0().iter_mut().for_each(sort);
+    } else {
+        queries.p1().iter_mut().for_each(sort);
+    }
+}
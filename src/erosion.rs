@@ -0,0 +1,348 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use noise::Perlin;
+use rand::Rng;
+
+use crate::chunk_grid::ChunkGrid;
+use crate::generation::GenerationConfig;
+use crate::macro_map::MacroMap;
+use crate::terrain::{chunk_rng, sample_grid, sample_noise_v3};
+use crate::*;
+
+// Droplets are seeded apart from the decorations of the chunk with the same position
+const EROSION_SEED_SALT: u32 = 0x85eb_ca6b;
+// How much of its direction a droplet keeps each step
+const DROPLET_INERTIA: f64 = 0.05;
+const DROPLET_GRAVITY: f64 = 4.0;
+// Keeps droplets on flat ground carrying some sediment
+const DROPLET_MIN_SLOPE: f64 = 0.01;
+// Tiles around a droplet it takes sediment from, wider brushes carve smoother valleys
+const EROSION_RADIUS: i32 = 2;
+
+/// Eroded regions of a world, regions are computed once and shared by every chunk
+/// that overlaps them, the oldest ones are dropped past `EROSION_CACHE_REGIONS`
+#[derive(Default)]
+pub struct ErosionCache {
+    regions: Mutex<ErodedRegions>,
+}
+
+#[derive(Default)]
+struct ErodedRegions {
+    deltas: HashMap<(i32, i32), Arc<ChunkGrid<f32>>>,
+    order: VecDeque<(i32, i32)>,
+}
+
+impl ErosionCache {
+    fn get_or_erode(
+        &self,
+        cell: (i32, i32),
+        erode: impl FnOnce() -> ChunkGrid<f32>,
+    ) -> Arc<ChunkGrid<f32>> {
+        if let Some(delta) = self.regions.lock().unwrap().deltas.get(&cell) {
+            return delta.clone();
+        }
+
+        // Eroded without the lock, two chunks racing for a region both get the same result
+        let delta = Arc::new(erode());
+        let mut regions = self.regions.lock().unwrap();
+        if regions.deltas.insert(cell, delta.clone()).is_none() {
+            regions.order.push_back(cell);
+        }
+        while regions.order.len() > EROSION_CACHE_REGIONS {
+            let oldest = regions.order.pop_front().unwrap();
+            regions.deltas.remove(&oldest);
+        }
+        delta
+    }
+}
+
+/// Elevation change erosion makes over a `w`×`h` area.
+/// Regions are eroded on their own and cross faded over `erosion_blend` tiles around
+/// their edges, a tile only depends on the regions around it so chunks tile
+pub fn erosion_grid(
+    gen_seed: u32,
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+    origin: (i32, i32),
+    w: usize,
+    h: usize,
+) -> ChunkGrid<f64> {
+    if config.erosion_droplets == 0 && config.thermal_iterations == 0 {
+        return ChunkGrid::new(origin, w, h, 0.0);
+    }
+
+    let (rw, rh) = (
+        config.erosion_region_tiles.0 as i32,
+        config.erosion_region_tiles.1 as i32,
+    );
+    let half = config.erosion_blend as i32 / 2;
+    let end = (origin.0 + w as i32 - 1, origin.1 + h as i32 - 1);
+
+    let mut regions = Vec::new();
+    for cy in (origin.1 - half).div_euclid(rh)..=(end.1 + half).div_euclid(rh) {
+        for cx in (origin.0 - half).div_euclid(rw)..=(end.0 + half).div_euclid(rw) {
+            let delta = macro_map.erosion.get_or_erode((cx, cy), || {
+                erode_region(gen_seed, config, macro_map, (cx, cy))
+            });
+            regions.push(((cx, cy), delta));
+        }
+    }
+
+    let band = config.erosion_blend.max(1) as f64;
+    let mut cells = Vec::with_capacity(w * h);
+    for j in 0..h as i32 {
+        for i in 0..w as i32 {
+            let (x, y) = (origin.0 + i, origin.1 + j);
+            let delta = regions
+                .iter()
+                .map(|((cx, cy), delta)| {
+                    let weight = blend_weight(x, *cx, rw, band) * blend_weight(y, *cy, rh, band);
+                    if weight > 0.0 {
+                        weight * delta.get((x, y)).unwrap_or_default() as f64
+                    } else {
+                        0.0
+                    }
+                })
+                .sum();
+            cells.push(delta);
+        }
+    }
+
+    ChunkGrid::from_cells(origin, w, h, cells)
+}
+
+/// Share of region `cell` in the erosion at `x` along one axis, ramps from 0 to 1
+/// over `band` tiles centered on each region edge, the shares of all regions add up to 1
+fn blend_weight(x: i32, cell: i32, size: i32, band: f64) -> f64 {
+    let x = x as f64 + 0.5;
+    let (start, end) = ((cell * size) as f64, ((cell + 1) * size) as f64);
+    let fade_in = ((x - start) / band + 0.5).clamp(0.0, 1.0);
+    let fade_out = ((end - x) / band + 0.5).clamp(0.0, 1.0);
+    fade_in * fade_out
+}
+
+/// Runs the droplets and thermal passes over one region and its margin,
+/// keeps the change over the region and its blend band
+fn erode_region(
+    gen_seed: u32,
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+    cell: (i32, i32),
+) -> ChunkGrid<f32> {
+    let (rw, rh) = config.erosion_region_tiles;
+    let half = config.erosion_blend / 2 + 1;
+    let margin = config.erosion_margin;
+
+    let blend_origin = (
+        cell.0 * rw as i32 - half as i32,
+        cell.1 * rh as i32 - half as i32,
+    );
+    let (bw, bh) = (rw + half * 2, rh + half * 2);
+
+    let noise = Perlin::new(gen_seed);
+    let origin = (
+        blend_origin.0 - margin as i32,
+        blend_origin.1 - margin as i32,
+    );
+    let (w, h) = (bw + margin * 2, bh + margin * 2);
+    let elevation = sample_grid(origin, w, h, |x, y| {
+        sample_noise_v3(&noise, config, macro_map, x, y).0
+    });
+
+    let mut heights = Heightmap {
+        w,
+        h,
+        cells: elevation
+            .iter()
+            .map(|(_, e)| e * config.erosion_height_scale)
+            .collect(),
+    };
+    let brush = erosion_brush();
+    let mut rng = chunk_rng(gen_seed ^ EROSION_SEED_SALT, cell);
+    for _ in 0..config.erosion_droplets {
+        let start = (
+            rng.gen_range(0.0..(w - 1) as f64),
+            rng.gen_range(0.0..(h - 1) as f64),
+        );
+        heights.run_droplet(config, &brush, start);
+    }
+    for _ in 0..config.thermal_iterations {
+        heights.thermal_pass(config);
+    }
+
+    let mut cells = Vec::with_capacity(bw * bh);
+    for j in 0..bh {
+        for i in 0..bw {
+            let (x, y) = (blend_origin.0 + i as i32, blend_origin.1 + j as i32);
+            let eroded = heights.cells[(j + margin) * w + i + margin];
+            let delta = eroded / config.erosion_height_scale - elevation.get((x, y)).unwrap();
+            cells.push(delta as f32);
+        }
+    }
+
+    ChunkGrid::from_cells(blend_origin, bw, bh, cells)
+}
+
+/// Offsets and weights of the cells a droplet erodes, weights fall off with distance
+/// and add up to 1
+fn erosion_brush() -> Vec<(i32, i32, f64)> {
+    let cells = (-EROSION_RADIUS..=EROSION_RADIUS)
+        .flat_map(|dy| (-EROSION_RADIUS..=EROSION_RADIUS).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| {
+            let dist = ((dx * dx + dy * dy) as f64).sqrt();
+            (dx, dy, EROSION_RADIUS as f64 - dist)
+        })
+        .filter(|&(_, _, weight)| weight > 0.0)
+        .collect::<Vec<_>>();
+    let total = cells.iter().map(|(_, _, weight)| weight).sum::<f64>();
+    cells
+        .into_iter()
+        .map(|(dx, dy, weight)| (dx, dy, weight / total))
+        .collect()
+}
+
+/// Region elevations scaled to tiles, so slopes are height per tile
+struct Heightmap {
+    w: usize,
+    h: usize,
+    cells: Vec<f64>,
+}
+
+impl Heightmap {
+    /// Bilinear height and gradient at a position inside the map
+    fn sample(&self, (x, y): (f64, f64)) -> (f64, (f64, f64)) {
+        let (cx, cy) = (x as usize, y as usize);
+        let (u, v) = (x - cx as f64, y - cy as f64);
+        let i = cy * self.w + cx;
+        let (nw, ne) = (self.cells[i], self.cells[i + 1]);
+        let (sw, se) = (self.cells[i + self.w], self.cells[i + self.w + 1]);
+
+        let height =
+            nw * (1.0 - u) * (1.0 - v) + ne * u * (1.0 - v) + sw * (1.0 - u) * v + se * u * v;
+        let gradient = (
+            (ne - nw) * (1.0 - v) + (se - sw) * v,
+            (sw - nw) * (1.0 - u) + (se - ne) * u,
+        );
+        (height, gradient)
+    }
+
+    /// Adds `amount` to the four cells around a position, weighted by distance
+    fn add(&mut self, (x, y): (f64, f64), amount: f64) {
+        let (cx, cy) = (x as usize, y as usize);
+        let (u, v) = (x - cx as f64, y - cy as f64);
+        let i = cy * self.w + cx;
+        self.cells[i] += amount * (1.0 - u) * (1.0 - v);
+        self.cells[i + 1] += amount * u * (1.0 - v);
+        self.cells[i + self.w] += amount * (1.0 - u) * v;
+        self.cells[i + self.w + 1] += amount * u * v;
+    }
+
+    /// Takes `amount` from the cells within `EROSION_RADIUS` of a position,
+    /// closer cells give more, returns what was taken inside the map
+    fn erode(&mut self, (x, y): (f64, f64), amount: f64, brush: &[(i32, i32, f64)]) -> f64 {
+        let (cx, cy) = (x as i32, y as i32);
+        let mut taken = 0.0;
+        for &(dx, dy, weight) in brush {
+            let (bx, by) = (cx + dx, cy + dy);
+            if bx >= 0 && by >= 0 && bx < self.w as i32 && by < self.h as i32 {
+                self.cells[by as usize * self.w + bx as usize] -= amount * weight;
+                taken += amount * weight;
+            }
+        }
+        taken
+    }
+
+    /// A drop of water rolls downhill, picking up sediment where it speeds up
+    /// and dropping it in pits and where it slows down
+    fn run_droplet(
+        &mut self,
+        config: &GenerationConfig,
+        brush: &[(i32, i32, f64)],
+        mut pos: (f64, f64),
+    ) {
+        let (mut dir, mut speed, mut water, mut sediment) = ((0.0, 0.0), 1.0, 1.0, 0.0);
+        for _ in 0..config.droplet_lifetime {
+            let (height, gradient) = self.sample(pos);
+            dir = (
+                dir.0 * DROPLET_INERTIA - gradient.0 * (1.0 - DROPLET_INERTIA),
+                dir.1 * DROPLET_INERTIA - gradient.1 * (1.0 - DROPLET_INERTIA),
+            );
+            let len = (dir.0 * dir.0 + dir.1 * dir.1).sqrt();
+            if len < f64::EPSILON {
+                break;
+            }
+            dir = (dir.0 / len, dir.1 / len);
+
+            let next = (pos.0 + dir.0, pos.1 + dir.1);
+            if next.0 < 0.0
+                || next.1 < 0.0
+                || next.0 >= (self.w - 1) as f64
+                || next.1 >= (self.h - 1) as f64
+            {
+                break;
+            }
+
+            let delta = self.sample(next).0 - height;
+            let capacity =
+                (-delta).max(DROPLET_MIN_SLOPE) * speed * water * config.sediment_capacity;
+            if delta > 0.0 || sediment > capacity {
+                // Fills the pit it's climbing out of, or drops what it can't carry
+                let amount = if delta > 0.0 {
+                    delta.min(sediment)
+                } else {
+                    (sediment - capacity) * config.deposition_rate
+                };
+                sediment -= amount;
+                self.add(pos, amount);
+            } else {
+                // Never digs deeper than the step it takes
+                let amount = ((capacity - sediment) * config.erosion_rate).min(-delta);
+                sediment += self.erode(pos, amount, brush);
+            }
+
+            speed = (speed * speed - delta * DROPLET_GRAVITY).max(0.0).sqrt();
+            water *= 1.0 - config.evaporation;
+            pos = next;
+        }
+    }
+
+    /// Material steeper than the talus slope slides to its lower neighbours
+    fn thermal_pass(&mut self, config: &GenerationConfig) {
+        let talus = config.talus_slope * config.erosion_height_scale;
+        let mut moved = vec![0.0; self.cells.len()];
+        for y in 0..self.h {
+            for x in 0..self.w {
+                let i = y * self.w + x;
+                let height = self.cells[i];
+                let neighbours = [
+                    (x > 0).then(|| i - 1),
+                    (x + 1 < self.w).then(|| i + 1),
+                    (y > 0).then(|| i - self.w),
+                    (y + 1 < self.h).then(|| i + self.w),
+                ];
+
+                let excess = |n: usize| (height - self.cells[n] - talus).max(0.0);
+                let (total, steepest) = neighbours
+                    .iter()
+                    .flatten()
+                    .fold((0.0, 0.0_f64), |(total, steepest), &n| {
+                        (total + excess(n), steepest.max(excess(n)))
+                    });
+                if total <= 0.0 {
+                    continue;
+                }
+
+                let amount = steepest * config.thermal_rate / 2.0;
+                moved[i] -= amount;
+                for &n in neighbours.iter().flatten() {
+                    moved[n] += amount * excess(n) / total;
+                }
+            }
+        }
+
+        for (cell, delta) in self.cells.iter_mut().zip(moved) {
+            *cell += delta;
+        }
+    }
+}
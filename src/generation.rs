@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::coastline::WarpLayer;
+use crate::generator::fnv1a;
+use crate::ron_loader::RonLoader;
+use crate::*;
+
+/// Loads the world generation config, with the `dev` feature it's watched
+/// and terrain regenerates whenever the file changes
+pub struct GenerationPlugin;
+
+/// Noise scales and biome thresholds used by the chunk generators, loaded from `*.generation.ron` files.
+/// Rolls are uniform in `0..1`, a decoration is placed when the roll is above the value
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct GenerationConfig {
+    /// Tiles per noise period of the four averaged octaves
+    pub octave_scales: [f64; 4],
+    /// Elevation above which tiles are land
+    pub sea_level: f64,
+    /// Land below this elevation stays bare
+    pub shore_elevation: f64,
+
+    pub dense_forest_elevation: f64,
+    pub dense_forest_forest: f64,
+    pub dense_forest_roll: f64,
+
+    pub forest_threshold: f64,
+    pub forest_roll: f64,
+    /// Above this roll forest trees may use their third variant
+    pub forest_variant_roll: f64,
+
+    /// Threshold of the fourth octave, sparse trees grow outside of forests
+    pub sparse_forest_threshold: f64,
+    pub sparse_forest_roll: f64,
+    /// Above this roll a sparse tree is the animated kind
+    pub sparse_tree_roll: f64,
+
+    pub bones_elevation: (f64, f64),
+    pub bones_roll: f64,
+
+    pub settlement_elevation: (f64, f64),
+    pub settlement_max_forest: f64,
+    pub settlement_roll: f64,
+    /// Second roll of settlement tiles, the rest may get settlement details
+    pub house_roll: f64,
+    /// Above this roll houses use their last two variants
+    pub house_variant_roll: f64,
+    pub settlement_detail_elevation: (f64, f64),
+    pub settlement_detail_max_forest: f64,
+    pub settlement_detail_roll: f64,
+
+    /// Tiles per macro map cell, about a chunk
+    pub macro_cell_tiles: (usize, usize),
+    /// Macro map cells per noise period of its two octaves
+    pub macro_scales: [f64; 2],
+    /// Macro values above this are continents, below `open_sea_threshold` open sea,
+    /// archipelagos are in between
+    pub continent_threshold: f64,
+    pub open_sea_threshold: f64,
+    /// Elevation added to the tiles of each region
+    pub continent_bias: f64,
+    pub archipelago_bias: f64,
+    pub open_sea_bias: f64,
+
+    /// Applied in order before the octaves are sampled, empty turns warping off
+    pub warp_layers: Vec<WarpLayer>,
+    /// Largest elevation change coast roughness makes, 0 for smooth coasts
+    pub coast_roughness: f64,
+    /// Tiles per noise period of the coast roughness
+    pub coast_roughness_scale: f64,
+    /// Elevation distance from sea level over which roughness fades out
+    pub coast_band: f64,
+    /// Cellular automata passes over the coastline, 0 keeps every speck of land
+    pub smoothing_passes: usize,
+
+    /// Tiles per erosion region, regions are eroded on their own
+    pub erosion_region_tiles: (usize, usize),
+    /// Tiles across the cross fade between neighbouring regions, at most a region size
+    pub erosion_blend: usize,
+    /// Tiles eroded around a region so droplets can run in from outside
+    pub erosion_margin: usize,
+    /// Droplets run per region, 0 turns hydraulic erosion off
+    pub erosion_droplets: usize,
+    /// Steps a droplet takes before it dries up
+    pub droplet_lifetime: usize,
+    /// Tiles per unit of elevation while eroding, higher makes slopes steeper
+    pub erosion_height_scale: f64,
+    /// Sediment a droplet carries per unit of speed, water and slope
+    pub sediment_capacity: f64,
+    /// Share of the free capacity a droplet picks up each step
+    pub erosion_rate: f64,
+    /// Share of the extra sediment a droplet drops each step
+    pub deposition_rate: f64,
+    /// Share of water a droplet loses each step
+    pub evaporation: f64,
+    /// Thermal erosion passes, 0 turns it off
+    pub thermal_iterations: usize,
+    /// Elevation difference per tile above which material slides down
+    pub talus_slope: f64,
+    /// Share of the excess slope moved each pass
+    pub thermal_rate: f64,
+}
+
+/// Config used for new chunks, the defaults apply until the file is loaded
+#[derive(Resource)]
+pub struct ActiveGenerationConfig {
+    handle: Handle<GenerationConfig>,
+    pub config: Arc<GenerationConfig>,
+    /// `GenerationConfig::digest` of `config`
+    pub digest: u16,
+}
+
+/// Sent whenever the loaded config differs from the active one, chunks generated before it are stale
+#[derive(Event)]
+pub struct GenerationConfigChangedEvent;
+
+impl Plugin for GenerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GenerationConfig>()
+            .register_asset_loader(RonLoader::<GenerationConfig>::new(&["generation.ron"]))
+            .init_resource::<ActiveGenerationConfig>()
+            .add_event::<GenerationConfigChangedEvent>()
+            .add_systems(PreUpdate, apply_generation_config);
+    }
+}
+
+impl FromWorld for ActiveGenerationConfig {
+    fn from_world(world: &mut World) -> Self {
+        let config = GenerationConfig::default();
+        Self {
+            handle: world.resource::<AssetServer>().load(GENERATION_CONFIG_PATH),
+            digest: config.digest(),
+            config: Arc::new(config),
+        }
+    }
+}
+
+fn apply_generation_config(
+    configs: Res<Assets<GenerationConfig>>,
+    mut active: ResMut<ActiveGenerationConfig>,
+    mut ev_asset: EventReader<AssetEvent<GenerationConfig>>,
+    mut ev_changed: EventWriter<GenerationConfigChangedEvent>,
+) {
+    let id = active.handle.id();
+    let updated = ev_asset.read().any(|ev| match ev {
+        AssetEvent::LoadedWithDependencies { id: ev_id } | AssetEvent::Modified { id: ev_id } => {
+            *ev_id == id
+        }
+        _ => false,
+    });
+    if !updated {
+        return;
+    }
+    let Some(config) = configs.get(id) else {
+        return;
+    };
+    // Loading a file equal to the defaults keeps the chunks generated so far
+    if *active.config == *config {
+        return;
+    }
+
+    active.digest = config.digest();
+    active.config = Arc::new(config.clone());
+    ev_changed.send(GenerationConfigChangedEvent);
+}
+
+impl GenerationConfig {
+    /// Recorded in world codes, any edit to the config changes the generated world
+    /// and most likely the digest
+    pub fn digest(&self) -> u16 {
+        let ron = ron::to_string(self).expect("generation config serializes");
+        fnv1a(ron.as_bytes()) as u16 & CONFIG_DIGEST_MASK
+    }
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            octave_scales: [100.5, 53.5, 43.5, 23.5],
+            sea_level: 0.0,
+            shore_elevation: 0.05,
+            dense_forest_elevation: 0.5,
+            dense_forest_forest: 0.98,
+            dense_forest_roll: 0.2,
+            forest_threshold: 0.5,
+            forest_roll: 0.4,
+            forest_variant_roll: 0.7,
+            sparse_forest_threshold: 0.4,
+            sparse_forest_roll: 0.9,
+            sparse_tree_roll: 0.78,
+            bones_elevation: (0.3, 0.5),
+            bones_roll: 0.98,
+            settlement_elevation: (0.1, 0.3),
+            settlement_max_forest: 0.4,
+            settlement_roll: 0.8,
+            house_roll: 0.98,
+            house_variant_roll: 0.75,
+            settlement_detail_elevation: (0.2, 0.3),
+            settlement_detail_max_forest: 0.3,
+            settlement_detail_roll: 0.9,
+            macro_cell_tiles: (120, 100),
+            macro_scales: [11.5, 4.5],
+            continent_threshold: 0.2,
+            open_sea_threshold: -0.2,
+            continent_bias: 0.25,
+            archipelago_bias: 0.0,
+            open_sea_bias: -0.4,
+            warp_layers: vec![
+                WarpLayer {
+                    scale: 80.5,
+                    strength: 20.0,
+                },
+                WarpLayer {
+                    scale: 20.5,
+                    strength: 5.0,
+                },
+            ],
+            coast_roughness: 0.06,
+            coast_roughness_scale: 6.5,
+            coast_band: 0.15,
+            smoothing_passes: 2,
+            erosion_region_tiles: (128, 128),
+            erosion_blend: 32,
+            erosion_margin: 16,
+            erosion_droplets: 8000,
+            droplet_lifetime: 40,
+            erosion_height_scale: 40.0,
+            sediment_capacity: 4.0,
+            erosion_rate: 0.3,
+            deposition_rate: 0.1,
+            evaporation: 0.02,
+            thermal_iterations: 8,
+            talus_slope: 0.02,
+            thermal_rate: 0.25,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use noise::Perlin;
+
+use crate::generation::GenerationConfig;
+use crate::macro_map::MacroMap;
+use crate::terrain::{
+    gen_chunk_v1, gen_chunk_v2, gen_chunk_v3, gen_chunk_v4, sample_noise, sample_noise_v2,
+    sample_noise_v3, ChunkData,
+};
+use crate::*;
+
+/// Generates the chunk at `pos` for a seed, the same inputs must always give the same chunk
+pub type ChunkGenFn =
+    fn(u32, (i32, i32), (usize, usize), &GenerationConfig, &MacroMap) -> ChunkData;
+/// Elevation and octaves of a single tile, as the chunk generator samples them
+pub type SampleFn = fn(&Perlin, &GenerationConfig, &MacroMap, i32, i32) -> (f64, [f64; 4]);
+
+/// A released revision of the world generator.
+/// Revisions are never edited once released, changes to the world go into a new version
+/// so seeds and world codes shared before keep generating the same world
+pub struct Generator {
+    pub version: u8,
+    pub description: &'static str,
+    pub gen_chunk: ChunkGenFn,
+    pub sample: SampleFn,
+}
+
+/// Every released generator, oldest first
+pub const GENERATORS: &[Generator] = &[
+    Generator {
+        version: 1,
+        description: "Averaged perlin octaves, decorations rolled per chunk",
+        gen_chunk: gen_chunk_v1,
+        sample: |noise, config, _, x, y| sample_noise(noise, config, x, y),
+    },
+    Generator {
+        version: 2,
+        description: "Perlin octaves biased into continents, archipelagos and open sea",
+        gen_chunk: gen_chunk_v2,
+        sample: sample_noise_v2,
+    },
+    Generator {
+        version: 3,
+        description: "Domain warped, rough coastlines smoothed by cellular automata",
+        gen_chunk: gen_chunk_v3,
+        sample: sample_noise_v3,
+    },
+    Generator {
+        version: 4,
+        description: "Hydraulic and thermal erosion over the v3 terrain",
+        gen_chunk: gen_chunk_v4,
+        // Erosion is left out of the level of detail preview
+        sample: sample_noise_v3,
+    },
+];
+
+/// Shareable world, a seed along with the generator version and generation config it was made with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WorldCode {
+    pub version: u8,
+    pub seed: u32,
+    /// `GenerationConfig::digest` of the config, unknown for codes made before it was recorded
+    pub config: Option<u16>,
+}
+
+// Crockford base32, no I, L, O or U so codes are easy to read out
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LEN: usize = 12;
+/// Codes without a config digest
+const LEGACY_CODE_LEN: usize = 10;
+
+impl Generator {
+    pub fn get(version: u8) -> Option<&'static Generator> {
+        GENERATORS.iter().find(|g| g.version == version)
+    }
+
+    /// Generator new worlds are made with
+    pub fn latest() -> &'static Generator {
+        Self::get(GENERATOR_VERSION).expect("GENERATOR_VERSION is registered")
+    }
+}
+
+impl WorldCode {
+    /// World made with the latest generator, its config is recorded once it's applied
+    pub fn new(seed: u32) -> Self {
+        Self {
+            version: GENERATOR_VERSION,
+            seed,
+            config: None,
+        }
+    }
+
+    pub fn generator(&self) -> Option<&'static Generator> {
+        Generator::get(self.version)
+    }
+
+    /// Same seed and generator, and no known difference in config
+    pub fn same_world(&self, other: &WorldCode) -> bool {
+        self.version == other.version
+            && self.seed == other.seed
+            && (self.config.is_none() || other.config.is_none() || self.config == other.config)
+    }
+
+    /// Twelve characters split in three groups, e.g. `0G0V-NWQ5-T8RC`,
+    /// worlds without a config digest keep the ten character codes, e.g. `010XD-WT5CM`
+    pub fn encode(&self) -> String {
+        let (payload, payload_bytes, len, group) = match self.config {
+            Some(config) => (
+                (self.version as u64) << 44
+                    | (self.seed as u64) << 12
+                    | (config & CONFIG_DIGEST_MASK) as u64,
+                7,
+                CODE_LEN,
+                4,
+            ),
+            None => (
+                (self.version as u64) << 32 | self.seed as u64,
+                5,
+                LEGACY_CODE_LEN,
+                5,
+            ),
+        };
+        let bits = payload << 8 | checksum(&payload.to_be_bytes()[8 - payload_bytes..]) as u64;
+
+        let mut code = String::with_capacity(len + len / group);
+        for i in (0..len).rev() {
+            code.push(CODE_ALPHABET[(bits >> (i * 5) & 31) as usize] as char);
+            if i > 0 && i % group == 0 {
+                code.push('-');
+            }
+        }
+        code
+    }
+
+    /// Case and separators are ignored, typos are caught by the checksum
+    pub fn decode(code: &str) -> Option<Self> {
+        let mut bits = 0u64;
+        let mut len = 0;
+        for c in code.chars().filter(|c| !matches!(c, '-' | ' ')) {
+            let c = match c.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            };
+            let value = CODE_ALPHABET.iter().position(|a| *a as char == c)?;
+            bits = bits << 5 | value as u64;
+            len += 1;
+            if len > CODE_LEN {
+                return None;
+            }
+        }
+
+        let payload_bytes = match len {
+            CODE_LEN => 7,
+            LEGACY_CODE_LEN if bits >> 48 == 0 => 5,
+            _ => return None,
+        };
+        let payload = bits >> 8;
+        if checksum(&payload.to_be_bytes()[8 - payload_bytes..]) != bits as u8 {
+            return None;
+        }
+
+        Some(if len == CODE_LEN {
+            Self {
+                version: (payload >> 44) as u8,
+                seed: (payload >> 12) as u32,
+                config: Some(payload as u16 & CONFIG_DIGEST_MASK),
+            }
+        } else {
+            Self {
+                version: (payload >> 32) as u8,
+                seed: payload as u32,
+                config: None,
+            }
+        })
+    }
+}
+
+/// Stable across platforms and releases, unlike the std hasher
+pub fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, b| {
+        (hash ^ *b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    fnv1a(bytes) as u8
+}
@@ -0,0 +1,448 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::chunk_grid::ChunkGrid;
+use crate::generation::{ActiveGenerationConfig, GenerationConfig};
+use crate::player::Player;
+use crate::terrain::{BiomeSample, ChunkData, ChunkSpawnedEvent, ChunkUnloadedEvent};
+use crate::utils::*;
+use crate::*;
+
+/// Labels connected walkable ground of the loaded chunks as islands,
+/// islands crossing chunk borders are merged as their chunks stream in.
+/// Press `I` to outline them
+pub struct IslandPlugin;
+
+/// Top left most tile of an island, stable once the whole island is loaded
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct IslandId(pub (i32, i32));
+
+/// Walkable tiles of an island per biome
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BiomeMix {
+    pub shore: usize,
+    pub plains: usize,
+    pub forest: usize,
+    pub dense_forest: usize,
+}
+
+/// Loaded part of a land mass, positions are terrain grid tiles
+#[derive(Clone, Debug)]
+pub struct Island {
+    pub id: IslandId,
+    /// Walkable tiles
+    pub area: usize,
+    /// Top left and bottom right tiles of the bounding box
+    pub min: (i32, i32),
+    pub max: (i32, i32),
+    pub biomes: BiomeMix,
+    /// Whether the island doesn't run into chunks that aren't loaded,
+    /// otherwise it may grow and change id as more of it streams in
+    pub complete: bool,
+    sum: (i64, i64),
+}
+
+/// Islands of the loaded chunks, rebuilt whenever chunks are loaded or unloaded
+#[derive(Resource, Default)]
+pub struct Islands {
+    chunks: HashMap<(i32, i32), ChunkIslands>,
+    islands: HashMap<IslandId, Island>,
+    /// Island of every part, by chunk and part index
+    parts: HashMap<((i32, i32), usize), IslandId>,
+    /// Tiles per chunk, taken from the labelled chunks
+    chunk_size: (i32, i32),
+}
+
+/// Connected ground within a single chunk
+struct ChunkIslands {
+    data: Arc<ChunkData>,
+    /// Part index of every walkable tile
+    labels: ChunkGrid<Option<usize>>,
+    parts: Vec<Island>,
+}
+
+#[derive(Resource, Default)]
+struct IslandOverlay(bool);
+
+#[derive(Component)]
+struct IslandText;
+
+impl Plugin for IslandPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Islands>()
+            .init_resource::<IslandOverlay>()
+            .add_systems(Startup, spawn_island_text)
+            .add_systems(Update, update_islands)
+            .add_systems(
+                Update,
+                (
+                    toggle_island_overlay,
+                    draw_island_overlay,
+                    update_island_text,
+                )
+                    .chain()
+                    .after(update_islands),
+            );
+    }
+}
+
+impl Islands {
+    pub fn get(&self, id: IslandId) -> Option<&Island> {
+        self.islands.get(&id)
+    }
+
+    /// Island a loaded tile is on, `None` for water and chunks that aren't loaded
+    pub fn at(&self, tile: (i32, i32)) -> Option<&Island> {
+        let (w, h) = self.chunk_size;
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let pos = (tile.0.div_euclid(w), tile.1.div_euclid(h));
+        let part = self.chunks.get(&pos)?.labels.get(tile)??;
+        self.islands.get(self.parts.get(&(pos, part))?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Island> {
+        self.islands.values()
+    }
+
+    /// Labels the ground of a chunk, islands are only merged across chunks by `rebuild`
+    pub fn insert_chunk(&mut self, data: Arc<ChunkData>, config: &GenerationConfig) {
+        let chunk = ChunkIslands::label(data, config);
+        self.chunk_size = (chunk.labels.width() as i32, chunk.labels.height() as i32);
+        self.chunks.insert(chunk.data.pos, chunk);
+    }
+
+    /// Drops a chunk unless it was already replaced by a newer version of it
+    pub fn remove_chunk(&mut self, data: &Arc<ChunkData>) {
+        if self
+            .chunks
+            .get(&data.pos)
+            .is_some_and(|chunk| Arc::ptr_eq(&chunk.data, data))
+        {
+            self.chunks.remove(&data.pos);
+        }
+    }
+
+    /// Merges the parts of all loaded chunks that touch across chunk borders
+    pub fn rebuild(&mut self) {
+        let keys = self
+            .chunks
+            .iter()
+            .flat_map(|(pos, chunk)| (0..chunk.parts.len()).map(|part| (*pos, part)))
+            .collect::<Vec<_>>();
+        let index = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (*key, i))
+            .collect::<HashMap<_, _>>();
+        let mut roots = (0..keys.len()).collect::<Vec<_>>();
+
+        for (pos, chunk) in self.chunks.iter() {
+            let origin = chunk.labels.origin();
+            let (w, h) = (chunk.labels.width() as i32, chunk.labels.height() as i32);
+            // Right and bottom borders, so every border is checked once
+            let right = (0..h).map(|j| ((origin.0 + w - 1, origin.1 + j), (1, 0)));
+            let bottom = (0..w).map(|i| ((origin.0 + i, origin.1 + h - 1), (0, 1)));
+            for ((x, y), (dx, dy)) in right.chain(bottom) {
+                let nei_pos = (pos.0 + dx, pos.1 + dy);
+                let Some(nei) = self.chunks.get(&nei_pos) else {
+                    continue;
+                };
+                let (Some(Some(a)), Some(Some(b))) =
+                    (chunk.labels.get((x, y)), nei.labels.get((x + dx, y + dy)))
+                else {
+                    continue;
+                };
+                union(&mut roots, index[&(*pos, a)], index[&(nei_pos, b)]);
+            }
+        }
+
+        let mut islands = HashMap::<usize, Island>::new();
+        for (i, (pos, part)) in keys.iter().enumerate() {
+            let mut island = self.chunks[pos].parts[*part].clone();
+            island.complete &= self.surrounded(*pos, &island);
+            let root = find(&mut roots, i);
+            match islands.get_mut(&root) {
+                Some(merged) => merged.merge(&island),
+                None => {
+                    islands.insert(root, island);
+                }
+            }
+        }
+
+        self.parts = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (*key, islands[&find(&mut roots, i)].id))
+            .collect();
+        self.islands = islands
+            .into_values()
+            .map(|island| (island.id, island))
+            .collect();
+    }
+
+    /// Whether every chunk a part runs into is loaded
+    fn surrounded(&self, pos: (i32, i32), part: &Island) -> bool {
+        let chunk = &self.chunks[&pos];
+        let origin = chunk.labels.origin();
+        let end = (
+            origin.0 + chunk.labels.width() as i32 - 1,
+            origin.1 + chunk.labels.height() as i32 - 1,
+        );
+        [
+            (part.min.0 == origin.0, (pos.0 - 1, pos.1)),
+            (part.max.0 == end.0, (pos.0 + 1, pos.1)),
+            (part.min.1 == origin.1, (pos.0, pos.1 - 1)),
+            (part.max.1 == end.1, (pos.0, pos.1 + 1)),
+        ]
+        .iter()
+        .all(|(touches, nei)| !touches || self.chunks.contains_key(nei))
+    }
+}
+
+impl ChunkIslands {
+    /// Flood fills the walkable ground of a chunk
+    fn label(data: Arc<ChunkData>, config: &GenerationConfig) -> Self {
+        let ground = &data.ground;
+        let (w, h) = (ground.width(), ground.height());
+        let mut labels = ChunkGrid::new(ground.origin(), w, h, None);
+        let mut parts = Vec::new();
+
+        for (start, is_ground) in ground.iter() {
+            if !is_ground || labels.get(start).flatten().is_some() {
+                continue;
+            }
+
+            // Tiles are visited row by row, the first tile of a part is its top left most
+            let mut part = Island::new(IslandId(start));
+            labels.set(start, Some(parts.len()));
+            let mut open = vec![start];
+            while let Some((x, y)) = open.pop() {
+                part.add((x, y), data.biomes.get((x, y)).unwrap_or_default(), config);
+                for nei in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if ground.get(nei) == Some(true) && labels.get(nei) == Some(None) {
+                        labels.set(nei, Some(parts.len()));
+                        open.push(nei);
+                    }
+                }
+            }
+            parts.push(part);
+        }
+
+        Self {
+            data,
+            labels,
+            parts,
+        }
+    }
+}
+
+impl Island {
+    fn new(id: IslandId) -> Self {
+        Self {
+            id,
+            area: 0,
+            min: id.0,
+            max: id.0,
+            biomes: BiomeMix::default(),
+            complete: true,
+            sum: (0, 0),
+        }
+    }
+
+    /// Mean position of the island's tiles
+    pub fn centroid(&self) -> Vec2 {
+        let area = self.area.max(1) as f32;
+        Vec2::new(self.sum.0 as f32 / area, self.sum.1 as f32 / area)
+    }
+
+    fn add(&mut self, (x, y): (i32, i32), biome: BiomeSample, config: &GenerationConfig) {
+        self.area += 1;
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+        self.sum = (self.sum.0 + x as i64, self.sum.1 + y as i64);
+        self.biomes.add(biome, config);
+    }
+
+    fn merge(&mut self, other: &Island) {
+        self.id = IslandId(min_by_row(self.id.0, other.id.0));
+        self.area += other.area;
+        self.min = (self.min.0.min(other.min.0), self.min.1.min(other.min.1));
+        self.max = (self.max.0.max(other.max.0), self.max.1.max(other.max.1));
+        self.sum = (self.sum.0 + other.sum.0, self.sum.1 + other.sum.1);
+        self.biomes.merge(&other.biomes);
+        self.complete &= other.complete;
+    }
+}
+
+impl BiomeMix {
+    /// Same thresholds the generator places trees with
+    fn add(&mut self, biome: BiomeSample, config: &GenerationConfig) {
+        let (elevation, forest) = (biome.elevation as f64, biome.forest as f64);
+        if elevation < config.shore_elevation {
+            self.shore += 1;
+        } else if elevation > config.dense_forest_elevation || forest > config.dense_forest_forest {
+            self.dense_forest += 1;
+        } else if forest > config.forest_threshold {
+            self.forest += 1;
+        } else {
+            self.plains += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &BiomeMix) {
+        self.shore += other.shore;
+        self.plains += other.plains;
+        self.forest += other.forest;
+        self.dense_forest += other.dense_forest;
+    }
+}
+
+fn min_by_row(a: (i32, i32), b: (i32, i32)) -> (i32, i32) {
+    if (a.1, a.0) <= (b.1, b.0) {
+        a
+    } else {
+        b
+    }
+}
+
+fn find(roots: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while roots[root] != root {
+        root = roots[root];
+    }
+    roots[i] = root;
+    root
+}
+
+fn union(roots: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(roots, a), find(roots, b));
+    roots[a.max(b)] = a.min(b);
+}
+
+fn update_islands(
+    gen_config: Res<ActiveGenerationConfig>,
+    mut islands: ResMut<Islands>,
+    mut ev_spawned: EventReader<ChunkSpawnedEvent>,
+    mut ev_unloaded: EventReader<ChunkUnloadedEvent>,
+) {
+    if ev_spawned.is_empty() && ev_unloaded.is_empty() {
+        return;
+    }
+
+    for ev in ev_unloaded.read() {
+        // A replacement of the chunk may already be labelled
+        islands.remove_chunk(&ev.data);
+    }
+    for ev in ev_spawned.read() {
+        islands.insert_chunk(ev.data.clone(), &gen_config.config);
+    }
+    islands.rebuild();
+}
+
+fn toggle_island_overlay(keys: Res<Input<KeyCode>>, mut overlay: ResMut<IslandOverlay>) {
+    if keys.just_pressed(KeyCode::I) {
+        overlay.0 = !overlay.0;
+    }
+}
+
+/// Bounding boxes and centroids, complete islands in green
+fn draw_island_overlay(overlay: Res<IslandOverlay>, islands: Res<Islands>, mut gizmos: Gizmos) {
+    if !overlay.0 {
+        return;
+    }
+
+    let tile = Vec2::new(
+        (TILE_W * SPRITE_SCALE_FACTOR) as f32,
+        (TILE_H * SPRITE_SCALE_FACTOR) as f32,
+    );
+    for island in islands.iter() {
+        let color = if island.complete {
+            Color::GREEN
+        } else {
+            Color::ORANGE
+        };
+        let min = Vec2::from(top_left_grid_to_world(island.min.0, island.min.1));
+        let max = Vec2::from(top_left_grid_to_world(island.max.0, island.max.1));
+        let size = (max - min).abs() + tile;
+        gizmos.rect_2d((min + max) / 2.0, 0.0, size, color);
+
+        let centroid = island.centroid();
+        let (x, y) = top_left_grid_to_world(centroid.x.round() as i32, centroid.y.round() as i32);
+        gizmos.circle_2d(Vec2::new(x, y), tile.y, color);
+    }
+}
+
+fn spawn_island_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(52.0),
+            left: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+        IslandText,
+        Visibility::Hidden,
+    ));
+}
+
+/// Stats of the island under the player while the overlay is on
+fn update_island_text(
+    overlay: Res<IslandOverlay>,
+    islands: Res<Islands>,
+    player_query: Query<&Transform, With<Player>>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<IslandText>>,
+) {
+    let Ok(transform) = player_query.get_single() else {
+        return;
+    };
+    let (x, y) = world_to_grid(transform.translation.x, transform.translation.y);
+    let (x, y) = center_to_top_left_grid(x, y);
+
+    let value = match islands.at((x as i32, y as i32)) {
+        Some(island) => {
+            let percent = |count: usize| count * 100 / island.area.max(1);
+            format!(
+                "Island {:?}{}  {} tiles  shore {}% plains {}% forest {}% dense {}%",
+                island.id.0,
+                if island.complete {
+                    ""
+                } else {
+                    " (partly loaded)"
+                },
+                island.area,
+                percent(island.biomes.shore),
+                percent(island.biomes.plains),
+                percent(island.biomes.forest),
+                percent(island.biomes.dense_forest),
+            )
+        }
+        None => format!("Open water  {} islands loaded", islands.islands.len()),
+    };
+    let shown = if overlay.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for (mut text, mut visibility) in text_query.iter_mut() {
+        if *visibility != shown {
+            *visibility = shown;
+        }
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -1,6 +1,26 @@
+pub mod chunk_cache;
+pub mod chunk_gen;
+pub mod chunk_grid;
+pub mod coastline;
 pub mod configs;
+pub mod diagnostics;
+pub mod draw_order;
+pub mod erosion;
+pub mod generation;
+pub mod generator;
+pub mod islands;
+pub mod lod;
+pub mod macro_map;
+pub mod names;
+pub mod palette;
 pub mod player;
+pub mod ron_loader;
+pub mod seed;
+pub mod settings;
+pub mod spawn_point;
 pub mod terrain;
+pub mod tile_animation;
+pub mod tileset;
 pub mod utils;
 
 pub use configs::*;
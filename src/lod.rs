@@ -0,0 +1,369 @@
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy::utils::HashMap;
+use noise::Perlin;
+
+use crate::draw_order::DrawLayer;
+use crate::generation::{ActiveGenerationConfig, GenerationConfig};
+use crate::generator::{Generator, SampleFn};
+use crate::macro_map::{ActiveMacroMap, MacroMap};
+use crate::palette::{PaletteChangedEvent, PaletteThemes};
+use crate::settings::WorldSettings;
+use crate::terrain::{
+    update_required_chunks, BiomeSample, CurrentChunks, GenerationSeed, GeneratorVersion,
+};
+use crate::utils::*;
+use crate::*;
+
+/// Renders chunks that are far away or seen from a zoomed out camera
+/// as a single texture sampled straight from the generator
+pub struct LodPlugin;
+
+#[derive(Component)]
+struct LodChunk;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LodLevel {
+    /// One pixel per tile
+    Tile,
+    /// One pixel per `lod_coarse_tiles`×`lod_coarse_tiles` tiles
+    Coarse,
+}
+
+#[derive(Resource, Default)]
+pub struct LodChunks {
+    pub desired: HashMap<(i32, i32), LodLevel>,
+    spawned: HashMap<(i32, i32), SpawnedLod>,
+    /// Textures being sampled off the main thread
+    tasks: HashMap<(i32, i32), (LodLevel, Task<LodSamples>)>,
+    center: (i32, i32),
+}
+
+/// Generator samples of a texture, one per pixel in row order
+struct LodSamples {
+    width: usize,
+    height: usize,
+    biomes: Vec<BiomeSample>,
+}
+
+/// Samples are kept so a new palette only recolors the texture
+struct SpawnedLod {
+    level: LodLevel,
+    entity: Entity,
+    image: Handle<Image>,
+    samples: LodSamples,
+}
+
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LodChunks::default()).add_systems(
+            Update,
+            (
+                update_lod_chunks,
+                queue_lod_chunks,
+                spawn_lod_chunks,
+                recolor_lod_chunks,
+            )
+                .chain()
+                .after(update_required_chunks),
+        );
+    }
+}
+
+fn update_lod_chunks(
+    settings: Res<WorldSettings>,
+    current_chunks: Res<CurrentChunks>,
+    cam_query: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut lod_chunks: ResMut<LodChunks>,
+) {
+    let mut desired = HashMap::new();
+
+    for (camera, transform, projection) in cam_query.iter() {
+        if !camera.is_active {
+            continue;
+        }
+
+        let center = transform.translation().truncate();
+        let ((x1, y1), (x2, y2)) =
+            visible_terrain_chunks(center, projection.area, settings.chunk_size);
+        let (cx, cy) = world_to_terrain_chunk(center.x, center.y, settings.chunk_size);
+        lod_chunks.center = (cx, cy);
+
+        let span = settings.max_lod_chunk_span;
+        let (x1, x2) = ((x1 - 1).max(cx - span), (x2 + 1).min(cx + span));
+        let (y1, y2) = ((y1 - 1).max(cy - span), (y2 + 1).min(cy + span));
+        for i in x1..=x2 {
+            for j in y1..=y2 {
+                // Chunks with all their sprites don't need a texture underneath,
+                // chunks still generating or spawning keep theirs so there are no holes
+                if current_chunks
+                    .0
+                    .get(&(i, j))
+                    .is_some_and(|chunk| chunk.is_complete() && !chunk.stale)
+                {
+                    continue;
+                }
+
+                let dist = i.abs_diff(cx).max(j.abs_diff(cy));
+                let level = if projection.scale > settings.lod_coarse_min_scale
+                    || dist > settings.lod_tile_radius
+                {
+                    LodLevel::Coarse
+                } else {
+                    LodLevel::Tile
+                };
+
+                // Multiple cameras, keep the most detailed level
+                let entry = desired.entry((i, j)).or_insert(level);
+                if level == LodLevel::Tile {
+                    *entry = level;
+                }
+            }
+        }
+    }
+
+    if desired != lod_chunks.desired {
+        lod_chunks.desired = desired;
+    }
+}
+
+/// Samples missing textures on `AsyncComputeTaskPool`, a texture changing level
+/// stays on screen until its replacement is ready
+fn queue_lod_chunks(
+    mut commands: Commands,
+    settings: Res<WorldSettings>,
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    gen_config: Res<ActiveGenerationConfig>,
+    macro_map: Res<ActiveMacroMap>,
+    mut lod_chunks: ResMut<LodChunks>,
+) {
+    // Textures of the old world are useless after a reset or a config change
+    let world_changed = seed.is_changed()
+        || version.is_changed()
+        || gen_config.is_changed()
+        || macro_map.is_changed();
+    let lod_chunks = &mut *lod_chunks;
+    if world_changed {
+        lod_chunks.tasks.clear();
+    }
+    lod_chunks.spawned.retain(|pos, spawned| {
+        let keep = !world_changed && lod_chunks.desired.contains_key(pos);
+        if !keep {
+            commands.entity(spawned.entity).despawn();
+        }
+        keep
+    });
+    lod_chunks.tasks.retain(|pos, (level, _)| {
+        lod_chunks.desired.get(pos) == Some(level)
+            && lod_chunks.spawned.get(pos).map(|s| s.level) != Some(*level)
+    });
+
+    let free = settings
+        .lod_chunks_per_frame
+        .saturating_sub(lod_chunks.tasks.len());
+    if free == 0 {
+        return;
+    }
+    let (cx, cy) = lod_chunks.center;
+    let mut missing = lod_chunks
+        .desired
+        .iter()
+        .filter(|(pos, level)| {
+            lod_chunks.spawned.get(*pos).map(|s| s.level) != Some(**level)
+                && !lod_chunks.tasks.contains_key(*pos)
+        })
+        .map(|(pos, level)| (*pos, *level))
+        .collect::<Vec<_>>();
+    missing.sort_by_key(|((x, y), _)| x.abs_diff(cx) + y.abs_diff(cy));
+
+    let sample = Generator::get(version.0)
+        .unwrap_or_else(Generator::latest)
+        .sample;
+    let pool = AsyncComputeTaskPool::get();
+    for (pos, level) in missing.into_iter().take(free) {
+        let gen_seed = seed.0;
+        let config = gen_config.config.clone();
+        let macro_map = macro_map.0.clone();
+        let step = match level {
+            LodLevel::Tile => 1,
+            LodLevel::Coarse => settings.lod_coarse_tiles,
+        };
+        let chunk_size = settings.chunk_size;
+        let task = pool.spawn(async move {
+            sample_lod(sample, gen_seed, &config, &macro_map, chunk_size, pos, step)
+        });
+        lod_chunks.tasks.insert(pos, (level, task));
+    }
+}
+
+/// Turns finished samples into textures, replacing the old texture of the chunk if any
+fn spawn_lod_chunks(
+    mut commands: Commands,
+    settings: Res<WorldSettings>,
+    gen_config: Res<ActiveGenerationConfig>,
+    palette: Res<PaletteThemes>,
+    mut lod_chunks: ResMut<LodChunks>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let finished = lod_chunks
+        .tasks
+        .iter()
+        .filter(|(_, (_, task))| task.is_finished())
+        .map(|(pos, _)| *pos)
+        .collect::<Vec<_>>();
+
+    let (w, h) = settings.chunk_size;
+    let size = Vec2::new(
+        (w * TILE_W * SPRITE_SCALE_FACTOR) as f32,
+        (h * TILE_H * SPRITE_SCALE_FACTOR) as f32,
+    );
+    for pos in finished {
+        let Some((level, task)) = lod_chunks.tasks.remove(&pos) else {
+            continue;
+        };
+
+        let samples = block_on(task);
+        let image = images.add(lod_image(&settings, &gen_config.config, &palette, &samples));
+        let (x, y) = terrain_chunk_center(pos.0, pos.1, settings.chunk_size);
+        let entity = commands
+            .spawn((
+                SpriteBundle {
+                    texture: image.clone(),
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(vec3(x, y, DrawLayer::Lod.z())),
+                    ..default()
+                },
+                LodChunk,
+            ))
+            .id();
+        let spawned = SpawnedLod {
+            level,
+            entity,
+            image,
+            samples,
+        };
+        if let Some(old) = lod_chunks.spawned.insert(pos, spawned) {
+            commands.entity(old.entity).despawn();
+        }
+    }
+}
+
+/// A new palette recolors the existing textures in place
+fn recolor_lod_chunks(
+    settings: Res<WorldSettings>,
+    gen_config: Res<ActiveGenerationConfig>,
+    palette: Res<PaletteThemes>,
+    lod_chunks: Res<LodChunks>,
+    mut images: ResMut<Assets<Image>>,
+    mut ev_palette: EventReader<PaletteChangedEvent>,
+) {
+    if ev_palette.is_empty() {
+        return;
+    }
+
+    ev_palette.clear();
+    for spawned in lod_chunks.spawned.values() {
+        if let Some(image) = images.get_mut(&spawned.image) {
+            image.data = lod_pixels(&settings, &gen_config.config, &palette, &spawned.samples);
+        }
+    }
+}
+
+/// Samples every `step`th tile of a chunk, at the center of each `step`×`step` block
+fn sample_lod(
+    sample: SampleFn,
+    gen_seed: u32,
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+    (chunk_w, chunk_h): (usize, usize),
+    pos: (i32, i32),
+    step: usize,
+) -> LodSamples {
+    let noise = Perlin::new(gen_seed);
+    let (width, height) = (chunk_w / step, chunk_h / step);
+    let start = (pos.0 * chunk_w as i32, pos.1 * chunk_h as i32);
+
+    // Image rows go top to bottom, same as the terrain grid
+    let mut biomes = Vec::with_capacity(width * height);
+    for j in 0..height {
+        for i in 0..width {
+            let x = start.0 + (i * step + step / 2) as i32;
+            let y = start.1 + (j * step + step / 2) as i32;
+            let (elevation, [_, _, forest, _]) = sample(&noise, config, macro_map, x, y);
+            biomes.push(BiomeSample {
+                elevation: elevation as f32,
+                forest: forest as f32,
+            });
+        }
+    }
+
+    LodSamples {
+        width,
+        height,
+        biomes,
+    }
+}
+
+fn lod_image(
+    settings: &WorldSettings,
+    config: &GenerationConfig,
+    palette: &PaletteThemes,
+    samples: &LodSamples,
+) -> Image {
+    Image::new(
+        Extent3d {
+            width: samples.width as u32,
+            height: samples.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        lod_pixels(settings, config, palette, samples),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn lod_pixels(
+    settings: &WorldSettings,
+    config: &GenerationConfig,
+    palette: &PaletteThemes,
+    samples: &LodSamples,
+) -> Vec<u8> {
+    samples
+        .biomes
+        .iter()
+        .flat_map(|biome| lod_color(settings, config, palette, *biome))
+        .collect()
+}
+
+/// Coarse version of the biome thresholds the chunk generators place trees with,
+/// multiplied by the palette's biome color the way sprites are. Water is left transparent
+fn lod_color(
+    settings: &WorldSettings,
+    config: &GenerationConfig,
+    palette: &PaletteThemes,
+    biome: BiomeSample,
+) -> [u8; 4] {
+    let (elevation, forest) = (biome.elevation as f64, biome.forest as f64);
+    if elevation <= config.sea_level {
+        return [0, 0, 0, 0];
+    }
+
+    let color = if elevation > config.dense_forest_elevation || forest > config.dense_forest_forest
+    {
+        settings.lod_dense_forest_color
+    } else if forest > config.forest_threshold && elevation >= config.shore_elevation {
+        settings.lod_forest_color
+    } else {
+        settings.lod_ground_color
+    };
+    let [r, g, b, _] = palette.biome_color(biome, config).as_rgba_f32();
+    let tint = |c: u8, t: f32| (c as f32 * t).round().clamp(0.0, 255.0) as u8;
+
+    [tint(color.0, r), tint(color.1, g), tint(color.2, b), 255]
+}
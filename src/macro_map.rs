@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+
+use crate::chunk_grid::ChunkGrid;
+use crate::erosion::ErosionCache;
+use crate::generation::{ActiveGenerationConfig, GenerationConfig};
+use crate::terrain::GenerationSeed;
+use crate::*;
+
+/// Large scale layout of a world, one sample per `macro_cell_tiles` cell.
+/// Decides where continents, archipelagos and open sea go, chunk generators
+/// refine it with the per tile noise
+pub struct MacroMap {
+    noise: Perlin,
+    cell_size: (usize, usize),
+    scales: [f64; 2],
+    /// Cells around the origin, sampled up front
+    cells: ChunkGrid<f64>,
+    /// Eroded regions, filled as chunks ask for them
+    pub erosion: ErosionCache,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegionKind {
+    Continent,
+    Archipelago,
+    OpenSea,
+}
+
+/// Macro map of the current world and generation config
+#[derive(Resource, Clone)]
+pub struct ActiveMacroMap(pub Arc<MacroMap>);
+
+// The macro noise must not line up with the tile noise of the same seed
+const MACRO_SEED_SALT: u32 = 0x9e37_79b9;
+
+impl MacroMap {
+    /// Samples every cell within `MACRO_MAP_RADIUS` cells of the origin,
+    /// cells further away are sampled when asked for
+    pub fn new(gen_seed: u32, config: &GenerationConfig) -> Self {
+        let noise = Perlin::new(gen_seed ^ MACRO_SEED_SALT);
+        let size = MACRO_MAP_RADIUS as usize * 2 + 1;
+        let origin = (-MACRO_MAP_RADIUS, -MACRO_MAP_RADIUS);
+        let mut cells = Vec::with_capacity(size * size);
+        for j in 0..size as i32 {
+            for i in 0..size as i32 {
+                let cell = (origin.0 + i, origin.1 + j);
+                cells.push(sample_macro(&noise, config.macro_scales, cell));
+            }
+        }
+
+        Self {
+            noise,
+            cell_size: config.macro_cell_tiles,
+            scales: config.macro_scales,
+            cells: ChunkGrid::from_cells(origin, size, size, cells),
+            erosion: ErosionCache::default(),
+        }
+    }
+
+    /// Roughly in -1..1, continents are high and open sea low
+    pub fn continentalness(&self, cell: (i32, i32)) -> f64 {
+        self.cells
+            .get(cell)
+            .unwrap_or_else(|| sample_macro(&self.noise, self.scales, cell))
+    }
+
+    pub fn cell_of(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (w, h) = self.cell_size;
+        (x.div_euclid(w as i32), y.div_euclid(h as i32))
+    }
+
+    pub fn region(&self, config: &GenerationConfig, cell: (i32, i32)) -> RegionKind {
+        let value = self.continentalness(cell);
+        if value > config.continent_threshold {
+            RegionKind::Continent
+        } else if value < config.open_sea_threshold {
+            RegionKind::OpenSea
+        } else {
+            RegionKind::Archipelago
+        }
+    }
+
+    /// Elevation added to a tile, blended between the centers of the surrounding cells
+    /// so the bias is continuous across cell and chunk borders
+    pub fn elevation_bias(&self, config: &GenerationConfig, (x, y): (i32, i32)) -> f64 {
+        let (w, h) = (self.cell_size.0 as f64, self.cell_size.1 as f64);
+        let (fx, fy) = ((x as f64 + 0.5) / w - 0.5, (y as f64 + 0.5) / h - 0.5);
+        let (cx, cy) = (fx.floor() as i32, fy.floor() as i32);
+        let (tx, ty) = (fx - fx.floor(), fy - fy.floor());
+
+        let bias = |cell| region_bias(config, self.continentalness(cell));
+        let top = lerp(bias((cx, cy)), bias((cx + 1, cy)), tx);
+        let bottom = lerp(bias((cx, cy + 1)), bias((cx + 1, cy + 1)), tx);
+        lerp(top, bottom, ty)
+    }
+}
+
+/// Two octaves, the large one places the continents and the small one breaks up their edges
+fn sample_macro(noise: &Perlin, scales: [f64; 2], (x, y): (i32, i32)) -> f64 {
+    let [large, small] = scales.map(|s| noise.get([x as f64 / s, y as f64 / s]));
+    large * 0.7 + small * 0.3
+}
+
+/// Open sea sinks, continents rise and archipelagos are left to the tile noise,
+/// with ramps between them instead of steps
+fn region_bias(config: &GenerationConfig, value: f64) -> f64 {
+    let (sea, continent) = (config.open_sea_threshold, config.continent_threshold);
+    if value < sea {
+        config.open_sea_bias
+    } else if value > continent {
+        config.continent_bias
+    } else {
+        let t = (value - sea) / (continent - sea);
+        if t < 0.5 {
+            lerp(config.open_sea_bias, config.archipelago_bias, t * 2.0)
+        } else {
+            lerp(
+                config.archipelago_bias,
+                config.continent_bias,
+                t * 2.0 - 1.0,
+            )
+        }
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+impl FromWorld for ActiveMacroMap {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.resource::<GenerationSeed>().0;
+        let config = world
+            .get_resource::<ActiveGenerationConfig>()
+            .map(|active| active.config.clone())
+            .unwrap_or_default();
+        Self(Arc::new(MacroMap::new(seed, &config)))
+    }
+}
+
+/// Runs before chunks are queued, a new world or config gets a new macro map
+pub fn update_macro_map(
+    seed: Res<GenerationSeed>,
+    gen_config: Res<ActiveGenerationConfig>,
+    mut macro_map: ResMut<ActiveMacroMap>,
+) {
+    if seed.is_changed() || gen_config.is_changed() {
+        macro_map.0 = Arc::new(MacroMap::new(seed.0, &gen_config.config));
+    }
+}
@@ -5,10 +5,29 @@ use bevy::{
 };
 use bevy_pancam::{PanCam, PanCamPlugin};
 
-use island_procgen::{player::PlayerPlugin, terrain::TerrainPlugin};
-use island_procgen::{terrain::ResetTerrainEvent, *};
+use island_procgen::{
+    diagnostics::TerrainDiagnosticsPlugin, draw_order::DrawOrderPlugin,
+    generation::GenerationPlugin, islands::IslandPlugin, lod::LodPlugin, names::NamePlugin,
+    palette::PalettePlugin, player::PlayerPlugin, seed::SeedPlugin, terrain::TerrainPlugin,
+    tile_animation::TileAnimationPlugin, tileset::TilesetPlugin,
+};
+use island_procgen::{
+    palette::PaletteThemes, seed::parse_seed, settings::Settings, terrain::ResetTerrainEvent, *,
+};
 
 fn main() {
+    // Logging starts with the app, the error is reported by a startup system
+    let (settings, settings_error) = match Settings::load(SETTINGS_PATH) {
+        Ok(settings) => (settings, None),
+        Err(err) => (Settings::default(), Some(err.to_string())),
+    };
+    // `--seed` takes a number, a world code or any text
+    let world = std::env::args()
+        .skip_while(|arg| arg != "--seed")
+        .nth(1)
+        .and_then(|text| parse_seed(&text));
+    let (width, height) = settings.world.window_size;
+
     App::new()
         .add_plugins(
             DefaultPlugins
@@ -16,7 +35,7 @@ fn main() {
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         mode: bevy::window::WindowMode::Fullscreen,
-                        resolution: (WW as f32, WH as f32).into(),
+                        resolution: (width as f32, height as f32).into(),
                         title: "ProcGen".to_string(),
                         ..default()
                     }),
@@ -24,15 +43,32 @@ fn main() {
                 }),
         )
         .insert_resource(Msaa::Off)
-        .insert_resource(ClearColor(Color::rgba_u8(
-            BG_COLOR.0, BG_COLOR.1, BG_COLOR.2, 0,
-        )))
         .add_plugins(PanCamPlugin::default())
         .add_plugins(LogDiagnosticsPlugin::default())
         .add_plugins(FrameTimeDiagnosticsPlugin::default())
-        .add_plugins(TerrainPlugin)
-        .add_plugins(PlayerPlugin)
+        .add_plugins(TerrainDiagnosticsPlugin)
+        .add_plugins(TilesetPlugin)
+        .add_plugins(PalettePlugin)
+        .add_plugins(GenerationPlugin)
+        .add_plugins(TerrainPlugin {
+            settings: settings.world,
+            world,
+        })
+        .add_plugins(TileAnimationPlugin)
+        .add_plugins(DrawOrderPlugin)
+        .add_plugins(SeedPlugin)
+        .add_plugins(LodPlugin)
+        .add_plugins(IslandPlugin)
+        .add_plugins(NamePlugin)
+        .add_plugins(PlayerPlugin {
+            settings: settings.player,
+        })
         .add_systems(Startup, spawn_camera)
+        .add_systems(Startup, move || {
+            if let Some(err) = &settings_error {
+                warn!("{err}, using default settings");
+            }
+        })
         .add_systems(Update, handle_settings_input)
         .add_systems(Update, close_on_esc)
         .run();
@@ -44,10 +80,17 @@ fn spawn_camera(mut commands: Commands) {
         .insert(PanCam::default());
 }
 
-fn handle_settings_input(keys: Res<Input<KeyCode>>, mut writer: EventWriter<ResetTerrainEvent>) {
+fn handle_settings_input(
+    keys: Res<Input<KeyCode>>,
+    mut palette: ResMut<PaletteThemes>,
+    mut writer: EventWriter<ResetTerrainEvent>,
+) {
+    if keys.just_pressed(KeyCode::P) {
+        palette.next();
+    }
     if !keys.just_pressed(KeyCode::Tab) {
         return;
     }
 
-    writer.send(ResetTerrainEvent);
+    writer.send(ResetTerrainEvent(None));
 }
@@ -0,0 +1,403 @@
+use bevy::math::vec3;
+use bevy::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::utils::HashMap;
+use rand::Rng;
+
+use crate::draw_order::DrawLayer;
+use crate::generation::GenerationConfig;
+use crate::islands::Islands;
+use crate::player::{CurrentPlayerChunkPos, Player};
+use crate::terrain::{chunk_rng, ChunkData, ChunkSpawnedEvent, ChunkUnloadedEvent, GenerationSeed};
+use crate::utils::*;
+use crate::*;
+
+/// Names islands, villages, mountains and seas, the same world always gets the same names.
+/// Names are shown as map labels and in a banner whenever the player enters a new place
+pub struct NamePlugin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PlaceKind {
+    Island,
+    Village,
+    Mountain,
+    Sea,
+}
+
+/// Named feature of the world, positions are terrain grid tiles
+#[derive(Clone, Debug)]
+pub struct Place {
+    pub kind: PlaceKind,
+    /// Tile the name is seeded with
+    pub anchor: (i32, i32),
+    /// Where the label goes
+    pub center: Vec2,
+    /// Tiles the center is averaged over, parts of a place in several chunks merge by it
+    pub size: usize,
+    pub name: String,
+}
+
+/// Villages and mountains of the loaded chunks, islands and seas are named on demand
+#[derive(Resource, Default)]
+pub struct PlaceNames {
+    chunks: HashMap<(i32, i32), Arc<ChunkData>>,
+    places: Vec<Place>,
+}
+
+/// Place the player is in, the banner shows whenever it changes
+#[derive(Resource, Default)]
+struct CurrentPlace(Option<(PlaceKind, (i32, i32))>);
+
+#[derive(Resource, Default)]
+struct PlaceLabels(HashMap<(PlaceKind, (i32, i32)), (Place, Entity)>);
+
+#[derive(Component)]
+struct PlaceBanner(Timer);
+
+const ONSETS: &[&str] = &[
+    "", "b", "br", "c", "d", "dr", "f", "g", "gr", "h", "k", "l", "m", "n", "p", "r", "s", "st",
+    "t", "th", "v", "w", "z",
+];
+const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ae", "ai", "ea", "ou", "y"];
+const CODAS: &[&str] = &["", "", "", "n", "r", "l", "s", "th", "nd", "rk", "m"];
+
+impl Plugin for NamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaceNames>()
+            .init_resource::<CurrentPlace>()
+            .init_resource::<PlaceLabels>()
+            .add_systems(Startup, spawn_place_banner)
+            .add_systems(
+                Update,
+                (
+                    update_place_names,
+                    update_place_labels,
+                    show_place_banner,
+                    fade_place_banner,
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl PlaceKind {
+    /// Ways to build a full name from the generated word
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            PlaceKind::Island => &["{} Isle", "Isle of {}", "{}", "{} Island"],
+            PlaceKind::Village => &["{}", "{}ton", "{}by", "{}wick", "{} Haven"],
+            PlaceKind::Mountain => &["Mount {}", "{} Peak", "{} Crag"],
+            PlaceKind::Sea => &["{} Sea", "Sea of {}", "{} Deep", "{} Sound"],
+        }
+    }
+
+    /// Keeps places of different kinds at the same anchor from getting the same word
+    fn seed_salt(self) -> u32 {
+        match self {
+            PlaceKind::Island => 0x68e3_1da4,
+            PlaceKind::Village => 0xb529_7a4d,
+            PlaceKind::Mountain => 0x1b56_c4e9,
+            PlaceKind::Sea => 0x7f4a_7c15,
+        }
+    }
+}
+
+/// Name of a feature, seeded by the world seed, its kind and anchor tile
+pub fn place_name(gen_seed: u32, kind: PlaceKind, anchor: (i32, i32)) -> String {
+    let mut rng = chunk_rng(gen_seed ^ kind.seed_salt(), anchor);
+
+    // Open syllables with a closing consonant at the end keep names short and readable
+    let syllables = if rng.gen_bool(0.7) { 2 } else { 3 };
+    let mut word = String::new();
+    for _ in 0..syllables {
+        word.push_str(ONSETS[rng.gen_range(0..ONSETS.len())]);
+        word.push_str(VOWELS[rng.gen_range(0..VOWELS.len())]);
+    }
+    word.push_str(CODAS[rng.gen_range(0..CODAS.len())]);
+    let mut chars = word.chars();
+    let word = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => word,
+    };
+
+    let patterns = kind.patterns();
+    patterns[rng.gen_range(0..patterns.len())].replace("{}", &word)
+}
+
+/// Sea region a chunk belongs to, seas span `SEA_REGION_CHUNKS` chunks each way
+pub fn sea_region(chunk: (i32, i32)) -> (i32, i32) {
+    (
+        chunk.0.div_euclid(SEA_REGION_CHUNKS),
+        chunk.1.div_euclid(SEA_REGION_CHUNKS),
+    )
+}
+
+impl PlaceNames {
+    pub fn iter(&self) -> impl Iterator<Item = &Place> {
+        self.places.iter()
+    }
+
+    /// Village whose center is within `VILLAGE_RADIUS` tiles of a tile
+    pub fn village_at(&self, tile: (i32, i32)) -> Option<&Place> {
+        let tile = Vec2::new(tile.0 as f32, tile.1 as f32);
+        self.iter().find(|place| {
+            place.kind == PlaceKind::Village && place.center.distance(tile) <= VILLAGE_RADIUS as f32
+        })
+    }
+
+    /// Places of every loaded chunk, parts sharing an anchor are merged into one place
+    fn rebuild(&mut self) {
+        let mut places = HashMap::<_, Place>::new();
+        for place in self.chunks.values().flat_map(|chunk| chunk.places.iter()) {
+            places
+                .entry((place.kind, place.anchor))
+                .and_modify(|merged| merged.merge(place))
+                .or_insert_with(|| place.clone());
+        }
+        self.places = places.into_values().collect();
+    }
+}
+
+impl Place {
+    fn merge(&mut self, other: &Place) {
+        let size = self.size + other.size;
+        self.center =
+            (self.center * self.size as f32 + other.center * other.size as f32) / size as f32;
+        self.size = size;
+    }
+}
+
+/// Cell of the global village grid a house is in, named by its top left tile
+pub fn village_anchor((x, y): (i32, i32)) -> (i32, i32) {
+    let cell = VILLAGE_CELL_TILES;
+    (x.div_euclid(cell) * cell, y.div_euclid(cell) * cell)
+}
+
+/// Villages are the houses of one `VILLAGE_CELL_TILES` cell, a village across a chunk border
+/// is found on both sides with the same anchor and merged once both are loaded.
+/// Mountains are the highest peak of a chunk above the dense forest line, a peak is higher
+/// than all eight tiles around it and never on the chunk border, so the same mountain
+/// isn't named again by the chunk next to it
+pub fn chunk_places(gen_seed: u32, chunk: &ChunkData, config: &GenerationConfig) -> Vec<Place> {
+    let mut villages = HashMap::<_, (Vec2, usize)>::new();
+    for t in chunk.tiles.iter().filter(|t| t.name == "house") {
+        let (sum, houses) = villages.entry(village_anchor(t.pos)).or_default();
+        *sum += Vec2::new(t.pos.0 as f32, t.pos.1 as f32);
+        *houses += 1;
+    }
+    let mut places = villages
+        .into_iter()
+        .map(|(anchor, (sum, houses))| Place {
+            kind: PlaceKind::Village,
+            anchor,
+            center: sum / houses as f32,
+            size: houses,
+            name: place_name(gen_seed, PlaceKind::Village, anchor),
+        })
+        .collect::<Vec<_>>();
+    places.sort_by_key(|place| (place.anchor.1, place.anchor.0));
+
+    let is_peak = |(x, y): (i32, i32), elevation: f32| {
+        (-1..=1)
+            .flat_map(|i| (-1..=1).map(move |j| (i, j)))
+            .filter(|offset| *offset != (0, 0))
+            .all(|(i, j)| {
+                chunk
+                    .biomes
+                    .get((x + i, y + j))
+                    .is_some_and(|biome| biome.elevation < elevation)
+            })
+    };
+    let peak = chunk
+        .biomes
+        .iter()
+        .filter(|(pos, biome)| is_peak(*pos, biome.elevation))
+        .max_by(|(_, a), (_, b)| a.elevation.total_cmp(&b.elevation));
+    if let Some((anchor, biome)) = peak {
+        if biome.elevation as f64 > config.dense_forest_elevation {
+            places.push(Place {
+                kind: PlaceKind::Mountain,
+                anchor,
+                center: Vec2::new(anchor.0 as f32, anchor.1 as f32),
+                size: 1,
+                name: place_name(gen_seed, PlaceKind::Mountain, anchor),
+            });
+        }
+    }
+
+    places
+}
+
+fn update_place_names(
+    mut names: ResMut<PlaceNames>,
+    mut ev_spawned: EventReader<ChunkSpawnedEvent>,
+    mut ev_unloaded: EventReader<ChunkUnloadedEvent>,
+) {
+    if ev_spawned.is_empty() && ev_unloaded.is_empty() {
+        return;
+    }
+
+    for ev in ev_unloaded.read() {
+        names.chunks.remove(&ev.pos);
+    }
+    for ev in ev_spawned.read() {
+        names.chunks.insert(ev.pos, ev.data.clone());
+    }
+    names.rebuild();
+}
+
+/// Labels villages, mountains and every fully loaded island big enough to be worth a name
+fn update_place_labels(
+    mut commands: Commands,
+    seed: Res<GenerationSeed>,
+    names: Res<PlaceNames>,
+    islands: Res<Islands>,
+    mut labels: ResMut<PlaceLabels>,
+) {
+    if !names.is_changed() && !islands.is_changed() && !seed.is_changed() {
+        return;
+    }
+
+    let island_places = islands
+        .iter()
+        .filter(|island| island.complete && island.area >= NAMED_ISLAND_MIN_TILES)
+        .map(|island| Place {
+            kind: PlaceKind::Island,
+            anchor: island.id.0,
+            center: island.centroid(),
+            size: island.area,
+            name: place_name(seed.0, PlaceKind::Island, island.id.0),
+        });
+    let desired = names
+        .iter()
+        .cloned()
+        .chain(island_places)
+        .map(|place| ((place.kind, place.anchor), place))
+        .collect::<HashMap<_, _>>();
+
+    // Merged villages move their label as more of them loads
+    labels.0.retain(|key, (label, entity)| {
+        let keep = desired
+            .get(key)
+            .is_some_and(|place| place.name == label.name && place.center == label.center);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+    for (key, place) in desired {
+        if labels.0.contains_key(&key) {
+            continue;
+        }
+
+        let (x, y) = top_left_grid_to_world(place.center.x as i32, place.center.y as i32);
+        let font_size = match place.kind {
+            PlaceKind::Island => 64.0,
+            _ => 40.0,
+        };
+        let entity = commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    place.name.clone(),
+                    TextStyle {
+                        font_size,
+                        color: Color::rgba(1.0, 1.0, 1.0, 0.85),
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(vec3(x, y, DrawLayer::Overlay.z())),
+                ..default()
+            })
+            .id();
+        labels.0.insert(key, (place, entity));
+    }
+}
+
+fn spawn_place_banner(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                top: Val::Percent(12.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 48.0,
+                        color: Color::NONE,
+                        ..default()
+                    },
+                ),
+                PlaceBanner(Timer::from_seconds(PLACE_BANNER_TIME, TimerMode::Once)),
+            ));
+        });
+}
+
+/// Most specific place under the player, a village, then the island, or else the sea
+fn show_place_banner(
+    seed: Res<GenerationSeed>,
+    names: Res<PlaceNames>,
+    islands: Res<Islands>,
+    chunk_pos: Res<CurrentPlayerChunkPos>,
+    mut current: ResMut<CurrentPlace>,
+    player_query: Query<&Transform, With<Player>>,
+    mut banner_query: Query<(&mut Text, &mut PlaceBanner)>,
+) {
+    let Ok(transform) = player_query.get_single() else {
+        return;
+    };
+    let (x, y) = world_to_grid(transform.translation.x, transform.translation.y);
+    let (x, y) = center_to_top_left_grid(x, y);
+    let tile = (x as i32, y as i32);
+
+    let place = if let Some(village) = names.village_at(tile) {
+        Some((PlaceKind::Village, village.anchor, village.name.clone()))
+    } else if let Some(island) = islands.at(tile) {
+        // Partly loaded islands may still change id, they are announced once complete
+        (island.complete && island.area >= NAMED_ISLAND_MIN_TILES).then(|| {
+            let name = place_name(seed.0, PlaceKind::Island, island.id.0);
+            (PlaceKind::Island, island.id.0, name)
+        })
+    } else {
+        let region = sea_region(chunk_pos.0);
+        Some((
+            PlaceKind::Sea,
+            region,
+            place_name(seed.0, PlaceKind::Sea, region),
+        ))
+    };
+    let Some((kind, anchor, name)) = place else {
+        return;
+    };
+    if current.0 == Some((kind, anchor)) && !seed.is_changed() {
+        return;
+    }
+
+    current.0 = Some((kind, anchor));
+    for (mut text, mut banner) in banner_query.iter_mut() {
+        text.sections[0].value = name.clone();
+        banner.0.reset();
+    }
+}
+
+fn fade_place_banner(time: Res<Time>, mut banner_query: Query<(&mut Text, &mut PlaceBanner)>) {
+    for (mut text, mut banner) in banner_query.iter_mut() {
+        if banner.0.finished() {
+            continue;
+        }
+
+        banner.0.tick(time.delta());
+        // Fades out over the last second
+        let left = banner.0.remaining().min(Duration::from_secs(1));
+        text.sections[0].style.color = Color::rgba(1.0, 1.0, 1.0, left.as_secs_f32());
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use crate::generation::GenerationConfig;
+use crate::ron_loader::RonLoader;
+use crate::settings::WorldSettings;
+use crate::terrain::BiomeSample;
+use crate::*;
+
+/// Loads palette themes, tints terrain sprites per biome and colors the water background
+pub struct PalettePlugin;
+
+/// Biome tint colors, loaded from `*.palette.ron` files
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct Palette {
+    /// Background color, the ocean is whatever isn't covered by tiles. `bg_color` in the
+    /// world settings takes priority
+    pub water: Color,
+    pub shore: Color,
+    pub plains: Color,
+    pub forest: Color,
+    pub dense_forest: Color,
+    /// Multiplied over the biome color of tiles with this name
+    #[serde(default)]
+    pub tiles: HashMap<String, Color>,
+}
+
+/// Available themes, set `active` to switch at runtime
+#[derive(Resource)]
+pub struct PaletteThemes {
+    pub paths: Vec<String>,
+    pub active: usize,
+    handle: Handle<Palette>,
+    palette: Option<Palette>,
+}
+
+/// Sent whenever a palette finished loading, tiles spawned before it have stale tints
+#[derive(Event)]
+pub struct PaletteChangedEvent;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Palette>()
+            .register_asset_loader(RonLoader::<Palette>::new(&["palette.ron"]))
+            .init_resource::<ClearColor>()
+            .init_resource::<PaletteThemes>()
+            .add_event::<PaletteChangedEvent>()
+            .add_systems(PreUpdate, (swap_palette, apply_palette).chain());
+    }
+}
+
+impl FromWorld for PaletteThemes {
+    fn from_world(world: &mut World) -> Self {
+        let paths = PALETTE_PATHS
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>();
+        let handle = world.resource::<AssetServer>().load(paths[0].clone());
+
+        Self {
+            paths,
+            active: 0,
+            handle,
+            palette: None,
+        }
+    }
+}
+
+fn swap_palette(asset_server: Res<AssetServer>, mut themes: ResMut<PaletteThemes>) {
+    let Some(active) = themes.paths.get(themes.active).cloned() else {
+        return;
+    };
+    let path = asset_server.get_path(themes.handle.id());
+    if path.is_some_and(|path| path.path().to_str() == Some(active.as_str())) {
+        return;
+    }
+
+    themes.handle = asset_server.load(active);
+}
+
+fn apply_palette(
+    palettes: Res<Assets<Palette>>,
+    mut themes: ResMut<PaletteThemes>,
+    settings: Res<WorldSettings>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ev_asset: EventReader<AssetEvent<Palette>>,
+    mut ev_changed: EventWriter<PaletteChangedEvent>,
+) {
+    let id = themes.handle.id();
+    let updated = ev_asset.read().any(|ev| match ev {
+        AssetEvent::LoadedWithDependencies { id: ev_id } | AssetEvent::Modified { id: ev_id } => {
+            *ev_id == id
+        }
+        _ => false,
+    });
+    if !updated {
+        return;
+    }
+    let Some(palette) = palettes.get(id) else {
+        return;
+    };
+
+    clear_color.0 = match settings.bg_color {
+        Some((r, g, b)) => Color::rgb_u8(r, g, b),
+        None => palette.water,
+    };
+    themes.palette = Some(palette.clone());
+    ev_changed.send(PaletteChangedEvent);
+}
+
+impl PaletteThemes {
+    pub fn next(&mut self) {
+        self.active = (self.active + 1) % self.paths.len().max(1);
+    }
+
+    /// Sprite color of a tile, white until a palette is loaded
+    pub fn tint(&self, name: &str, biome: BiomeSample, config: &GenerationConfig) -> Color {
+        let color = self.biome_color(biome, config);
+        match self
+            .palette
+            .as_ref()
+            .and_then(|palette| palette.tiles.get(name))
+        {
+            Some(tint) => Color::from(Vec4::from(color) * Vec4::from(*tint)),
+            None => color,
+        }
+    }
+
+    /// Blended biome color, without the tint of any particular tile
+    pub fn biome_color(&self, biome: BiomeSample, config: &GenerationConfig) -> Color {
+        let Some(palette) = self.palette.as_ref() else {
+            return Color::WHITE;
+        };
+
+        // Weights ease across the active generator thresholds so biome edges fade into each other
+        let sea = config.sea_level as f32;
+        let shore_elevation = config.shore_elevation as f32;
+        let forest_threshold = config.forest_threshold as f32;
+        let dense_elevation = config.dense_forest_elevation as f32;
+        let dense_forest = config.dense_forest_forest as f32;
+        let forest = smoothstep(forest_threshold - 0.1, forest_threshold + 0.1, biome.forest)
+            * smoothstep(sea, shore_elevation, biome.elevation);
+        let dense = smoothstep(
+            dense_elevation - 0.1,
+            dense_elevation + 0.05,
+            biome.elevation,
+        )
+        .max(smoothstep(dense_forest - 0.04, dense_forest, biome.forest));
+        let shore = 1.0 - smoothstep(sea, shore_elevation + 0.03, biome.elevation);
+
+        let color = mix(palette.plains, palette.forest, forest);
+        let color = mix(color, palette.dense_forest, dense);
+        mix(color, palette.shore, shore)
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    Color::from(Vec4::from(a).lerp(Vec4::from(b), t))
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
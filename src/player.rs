@@ -1,34 +1,55 @@
-use bevy::math::vec2;
-use bevy::{math::vec3, prelude::*, utils::Instant};
-
-use crate::terrain::GroundTiles;
+use bevy::{
+    math::vec3,
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+    utils::Instant,
+};
+
+use crate::chunk_cache::ChunkCache;
+use crate::draw_order::{DrawLayer, YSorted};
+use crate::generation::ActiveGenerationConfig;
+use crate::generator::Generator;
+use crate::macro_map::{update_macro_map, ActiveMacroMap};
+use crate::settings::{PlayerSettings, WorldSettings};
+use crate::spawn_point::{find_spawn_point, SpawnSearch, SpawnSearchResult};
+use crate::terrain::{CurrentChunks, GenerationSeed, GeneratorVersion, GroundTiles};
+use crate::tileset::TilesetAtlas;
 use crate::utils::*;
 use crate::*;
 
-pub struct PlayerPlugin;
+#[derive(Default)]
+pub struct PlayerPlugin {
+    pub settings: PlayerSettings,
+}
 
 #[derive(Component)]
 struct WalkTrail(Instant);
 #[derive(Component)]
-struct Player;
+pub struct Player;
 #[derive(Resource)]
 struct PlayerSpriteIndex(usize);
 #[derive(Component, Deref, DerefMut)]
 struct AnimationTimer(Timer);
-#[derive(Resource)]
-struct TextureAtlasHandle(Option<Handle<TextureAtlas>>);
 #[derive(Resource, Default)]
 struct CurrentPlayerState(PlayerState);
 #[derive(Resource)]
 struct PlayerDirection(f32);
 #[derive(Resource)]
 struct WalkTrailTimer(Timer);
-#[derive(Resource)]
-struct DefaultAtlasHandle(pub Option<Handle<TextureAtlas>>);
 #[derive(Resource, Default)]
 pub struct CurrentPlayerChunkPos(pub (i32, i32));
+/// World units per second the player moved last frame
+#[derive(Resource, Default)]
+pub struct PlayerVelocity(pub Vec2);
 #[derive(Event)]
 pub struct PlayerChunkUpdateEvent(pub (i32, i32));
+/// The spawn point search finished, `None` when there was no island around the spawn point
+#[derive(Event)]
+pub struct SpawnPointFoundEvent(pub Option<(i32, i32)>);
+/// Spawn point search of the current world and the config digest it was started with,
+/// the player is hidden and can't move until it finishes
+#[derive(Resource, Default)]
+struct SpawnPointTask(Option<(u16, Task<SpawnSearchResult>)>);
 
 // TODO make this a state
 #[derive(Default, PartialEq, Debug)]
@@ -46,16 +67,31 @@ impl Plugin for PlayerPlugin {
             .insert_resource(PlayerDirection(0.0))
             .insert_resource(CurrentPlayerState::default())
             .insert_resource(CurrentPlayerChunkPos::default())
+            .insert_resource(PlayerVelocity::default())
             .insert_resource(WalkTrailTimer(Timer::from_seconds(
-                WALK_TRAIL_TIMER,
+                self.settings.walk_trail_interval,
                 TimerMode::Repeating,
             )))
-            .insert_resource(DefaultAtlasHandle(None))
+            .insert_resource(self.settings.clone())
+            .init_resource::<SpawnPointTask>()
             .add_event::<PlayerChunkUpdateEvent>()
+            .add_event::<SpawnPointFoundEvent>()
             .add_systems(Startup, setup)
-            .add_systems(Update, update_player_state)
+            .add_systems(
+                Update,
+                (
+                    queue_spawn_point_search,
+                    poll_spawn_point_search,
+                    place_player,
+                )
+                    .chain()
+                    .after(update_macro_map),
+            )
+            .add_systems(
+                Update,
+                (update_player_state, handle_player_input).run_if(player_is_placed),
+            )
             .add_systems(Update, camera_follow_player)
-            .add_systems(Update, handle_player_input)
             .add_systems(Update, spawn_walk_trail)
             .add_systems(Update, update_player_chunk_pos)
             .add_systems(Update, clean_old_walk_trails)
@@ -63,49 +99,146 @@ impl Plugin for PlayerPlugin {
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    mut handle: ResMut<DefaultAtlasHandle>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-) {
-    let texture_handle = asset_server.load(SPRITE_SHEET_PATH);
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        vec2(TILE_W as f32, TILE_H as f32),
-        SPRITE_SHEET_W,
-        SPRITE_SHEET_H,
-        Some(Vec2::splat(SPRITE_PADDING)),
-        Some(Vec2::splat(SPRITE_SHEET_OFFSET)),
-    );
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    handle.0 = Some(texture_atlas_handle);
-
+fn setup(mut commands: Commands, settings: Res<PlayerSettings>, tileset: Res<TilesetAtlas>) {
     commands.spawn((
         SpriteSheetBundle {
-            texture_atlas: handle.0.clone().unwrap(),
-            sprite: TextureAtlasSprite::new(PLAYER_SPRITE_INDEX),
+            texture_atlas: tileset.atlas.clone(),
+            sprite: tileset.sprite("player_walk", 0),
             transform: Transform::from_scale(Vec3::splat(SPRITE_SCALE_FACTOR as f32))
-                .with_translation(vec3(0.0, 0.0, 2.0)),
+                .with_translation(vec3(0.0, 0.0, DrawLayer::Objects.z())),
             ..default()
         },
         Player,
+        YSorted(DrawLayer::Objects),
+        Visibility::Hidden,
         AnimationTimer(Timer::from_seconds(
-            PLAYER_ANIMATION_INTERVAL,
+            settings.animation_interval,
             TimerMode::Repeating,
         )),
     ));
 }
 
+/// Every new world, including the first one, looks for land to put the player on
+fn queue_spawn_point_search(
+    settings: Res<PlayerSettings>,
+    world_settings: Res<WorldSettings>,
+    gen_config: Res<ActiveGenerationConfig>,
+    macro_map: Res<ActiveMacroMap>,
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    mut task: ResMut<SpawnPointTask>,
+    mut player_query: Query<&mut Visibility, With<Player>>,
+) {
+    if !seed.is_changed() && !version.is_changed() {
+        return;
+    }
+
+    let (x, y) = world_to_grid(settings.spawn_point.0, settings.spawn_point.1);
+    let (x, y) = center_to_top_left_grid(x, y);
+    let search = SpawnSearch {
+        start: (x as i32, y as i32),
+        min_island_tiles: settings.spawn_min_island_tiles,
+        max_radius: settings.spawn_search_radius,
+    };
+    let gen_chunk = Generator::get(version.0)
+        .unwrap_or_else(Generator::latest)
+        .gen_chunk;
+    let gen_seed = seed.0;
+    let chunk_size = world_settings.chunk_size;
+    let config = gen_config.config.clone();
+    let macro_map = macro_map.0.clone();
+
+    // Replacing the task drops the search of the previous world
+    let pool = AsyncComputeTaskPool::get();
+    let search = pool.spawn(async move {
+        find_spawn_point(gen_chunk, gen_seed, chunk_size, &config, &macro_map, search)
+    });
+    task.0 = Some((gen_config.digest, search));
+    for mut visibility in player_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+/// The chunks searched go into the chunk cache so the terrain doesn't generate them again,
+/// unless the generation config changed in the meantime
+fn poll_spawn_point_search(
+    gen_config: Res<ActiveGenerationConfig>,
+    mut task: ResMut<SpawnPointTask>,
+    mut chunk_cache: ResMut<ChunkCache>,
+    mut ev_found: EventWriter<SpawnPointFoundEvent>,
+) {
+    if !task.0.as_ref().is_some_and(|(_, task)| task.is_finished()) {
+        return;
+    }
+    let Some((digest, task)) = task.0.take() else {
+        return;
+    };
+
+    let result = block_on(task);
+    if digest == gen_config.digest {
+        for chunk in result.chunks {
+            chunk_cache.insert(chunk);
+        }
+    }
+    ev_found.send(SpawnPointFoundEvent(result.spawn));
+}
+
+/// Moves the player to the spawn point once found, the player starts in the water
+/// at the configured point when there is no island around it
+fn place_player(
+    settings: Res<PlayerSettings>,
+    mut ev_found: EventReader<SpawnPointFoundEvent>,
+    mut player_state: ResMut<CurrentPlayerState>,
+    mut player_velocity: ResMut<PlayerVelocity>,
+    mut player_query: Query<(&mut Transform, &mut Visibility), With<Player>>,
+    mut cam_query: Query<&mut Transform, (With<Camera>, Without<Player>)>,
+) {
+    let Some(SpawnPointFoundEvent(spawn)) = ev_found.read().last() else {
+        return;
+    };
+    let (x, y) = match *spawn {
+        Some((x, y)) => top_left_grid_to_world(x, y),
+        None => {
+            warn!("no island to spawn on around {:?}", settings.spawn_point);
+            settings.spawn_point
+        }
+    };
+
+    for (mut transform, mut visibility) in player_query.iter_mut() {
+        transform.translation.x = x;
+        transform.translation.y = y;
+        *visibility = Visibility::Inherited;
+    }
+    // The camera jumps along instead of panning across the world
+    for mut cam_transform in cam_query.iter_mut() {
+        cam_transform.translation.x = x;
+        cam_transform.translation.y = y;
+    }
+    player_state.0 = PlayerState::Idle;
+    player_velocity.0 = Vec2::ZERO;
+}
+
+/// The player stays where it is while it's hidden, so chunks aren't streamed around it
+fn player_is_placed(task: Res<SpawnPointTask>) -> bool {
+    task.0.is_none()
+}
+
 fn update_player_state(
+    settings: Res<PlayerSettings>,
     mut player_state: ResMut<CurrentPlayerState>,
     mut sprite_index: ResMut<PlayerSpriteIndex>,
     ground_tiles: Res<GroundTiles>,
+    chunk_pos: Res<CurrentPlayerChunkPos>,
+    current_chunks: Res<CurrentChunks>,
     mut player_query: Query<&Transform, With<Player>>,
 ) {
     if player_query.is_empty() {
         return;
     }
+    // Ground isn't known until the chunk under the player is loaded
+    if !current_chunks.0.contains_key(&chunk_pos.0) {
+        return;
+    }
 
     let transform = player_query.single_mut();
     let (x, y) = (transform.translation.x, transform.translation.y);
@@ -122,7 +255,7 @@ fn update_player_state(
 
     match player_state.0 {
         PlayerState::Jump(jumped_at) => {
-            if jumped_at.elapsed().as_secs_f32() > PLAYER_JUMP_TIME {
+            if jumped_at.elapsed().as_secs_f32() > settings.jump_time {
                 player_state.0 = if is_ground {
                     PlayerState::Idle
                 } else {
@@ -137,6 +270,7 @@ fn update_player_state(
 
 fn update_player_sprite(
     time: Res<Time>,
+    tileset: Res<TilesetAtlas>,
     mut sprite_index: ResMut<PlayerSpriteIndex>,
     player_state: Res<CurrentPlayerState>,
     mut query: Query<(&mut TextureAtlasSprite, &mut AnimationTimer), With<Player>>,
@@ -155,16 +289,17 @@ fn update_player_sprite(
         sprite_index.0 = (sprite_index.0 + 1) % 3;
     }
 
-    sprite.index = if player_state.is_land() {
-        sprite_index.0 + PLAYER_SPRITE_INDEX
+    *sprite = if player_state.is_land() {
+        tileset.sprite("player_walk", sprite_index.0)
     } else if player_state.is_jump() {
-        sprite_index.0 + PLAYER_SPRITE_INDEX + 3
+        tileset.sprite("player_jump", sprite_index.0)
     } else {
-        49
+        tileset.sprite("player_swim", 0)
     };
 }
 
 fn update_player_chunk_pos(
+    settings: Res<WorldSettings>,
     mut chunk_pos: ResMut<CurrentPlayerChunkPos>,
     mut ev_chunk_update: EventWriter<PlayerChunkUpdateEvent>,
     player_query: Query<&Transform, With<Player>>,
@@ -177,7 +312,7 @@ fn update_player_chunk_pos(
     let (x, y) = (transform.translation.x, transform.translation.y);
     let (a, b) = world_to_grid(x, y);
     let (a, b) = center_to_top_left_grid(a, b);
-    let (x, y) = grid_to_chunk(a, b);
+    let (x, y) = grid_to_chunk(a, b, settings.chunk_size);
 
     let (old_x, old_y) = chunk_pos.0;
     if old_x == x && old_y == y {
@@ -189,8 +324,11 @@ fn update_player_chunk_pos(
 }
 
 fn handle_player_input(
+    time: Res<Time>,
+    settings: Res<PlayerSettings>,
     mut player_state: ResMut<CurrentPlayerState>,
     mut player_direction: ResMut<PlayerDirection>,
+    mut player_velocity: ResMut<PlayerVelocity>,
     mut player_query: Query<&mut Transform, With<Player>>,
     keys: Res<Input<KeyCode>>,
 ) {
@@ -234,13 +372,15 @@ fn handle_player_input(
             player_angle
         };
         let speed = if player_state.is_land() {
-            PLAYER_SPEED
+            settings.speed
         } else {
-            PLAYER_FISH_SPEED
+            settings.fish_speed
         };
         let new_pos = transform.translation + direction.normalize() * speed * speed_scale;
 
         if !new_pos.is_nan() {
+            let delta = (new_pos - transform.translation).truncate();
+            player_velocity.0 = delta / time.delta_seconds().max(f32::EPSILON);
             transform.translation = new_pos;
         }
 
@@ -252,6 +392,7 @@ fn handle_player_input(
             PlayerState::Swim
         };
     } else {
+        player_velocity.0 = Vec2::ZERO;
         player_state.0 = if player_state.is_land() {
             PlayerState::Idle
         } else {
@@ -265,7 +406,7 @@ fn spawn_walk_trail(
     mut commands: Commands,
     player_state: Res<CurrentPlayerState>,
     player_angle: Res<PlayerDirection>,
-    image_handle: Res<DefaultAtlasHandle>,
+    tileset: Res<TilesetAtlas>,
     mut timer: ResMut<WalkTrailTimer>,
     mut player_query: Query<&Transform, With<Player>>,
 ) {
@@ -281,10 +422,14 @@ fn spawn_walk_trail(
     let transform = player_query.single_mut();
     commands.spawn((
         SpriteSheetBundle {
-            texture_atlas: image_handle.0.clone().unwrap(),
-            sprite: TextureAtlasSprite::new(50),
+            texture_atlas: tileset.atlas.clone(),
+            sprite: tileset.sprite("walk_trail", 0),
             transform: Transform::from_scale(Vec3::splat(SPRITE_SCALE_FACTOR as f32 - 1.0))
-                .with_translation(vec3(transform.translation.x, transform.translation.y, 1.0))
+                .with_translation(vec3(
+                    transform.translation.x,
+                    transform.translation.y,
+                    DrawLayer::GroundDetail.z(),
+                ))
                 .with_rotation(Quat::from_rotation_z(player_angle.0)),
             ..default()
         },
@@ -294,6 +439,7 @@ fn spawn_walk_trail(
 
 fn clean_old_walk_trails(
     mut commands: Commands,
+    settings: Res<PlayerSettings>,
     query: Query<(Entity, &WalkTrail), With<WalkTrail>>,
 ) {
     if query.is_empty() {
@@ -301,7 +447,7 @@ fn clean_old_walk_trails(
     }
 
     for (entity, trail) in query.iter() {
-        if trail.0.elapsed().as_secs_f32() > TRAIL_LIFE_SPAN {
+        if trail.0.elapsed().as_secs_f32() > settings.trail_life_span {
             commands.entity(entity).despawn();
         }
     }
@@ -322,7 +468,7 @@ fn camera_follow_player(
         vec3(
             player_transform.translation.x,
             player_transform.translation.y,
-            0.0,
+            cam_transform.translation.z,
         ),
         0.05,
     );
@@ -0,0 +1,313 @@
+use std::io::ErrorKind;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::generation::ActiveGenerationConfig;
+use crate::generator::{fnv1a, WorldCode};
+use crate::terrain::{GenerationSeed, GeneratorVersion, MissingGenerator, ResetTerrainEvent};
+use crate::*;
+
+/// Shows the current seed and its world code, press Enter to type a new seed.
+/// Keeps the seeds of this session to go back and forth, favourites are bookmarked to a file
+pub struct SeedPlugin;
+
+/// Text typed into the seed field, keyboard input only goes to the field while it's open
+#[derive(Resource, Default)]
+pub struct SeedInput {
+    pub active: bool,
+    pub text: String,
+}
+
+/// Every world played this session, going back and starting a new world drops the forward worlds
+#[derive(Resource, Default)]
+pub struct SeedHistory {
+    worlds: Vec<WorldCode>,
+    index: usize,
+}
+
+/// Favourite worlds, saved as world codes
+#[derive(Resource)]
+pub struct SeedBookmarks {
+    pub path: String,
+    pub codes: Vec<WorldCode>,
+}
+
+#[derive(Component)]
+struct SeedText;
+
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeedInput>()
+            .init_resource::<SeedHistory>()
+            .insert_resource(SeedBookmarks::load(BOOKMARKS_PATH))
+            .add_systems(Startup, spawn_seed_text)
+            .add_systems(PreUpdate, handle_seed_input.after(InputSystem))
+            .add_systems(Update, (handle_seed_keys, record_seed_history).chain())
+            .add_systems(Update, update_seed_text.after(record_seed_history));
+    }
+}
+
+impl SeedHistory {
+    fn visit(&mut self, world: WorldCode) {
+        if self
+            .worlds
+            .get(self.index)
+            .is_some_and(|visited| visited.same_world(&world))
+        {
+            return;
+        }
+
+        self.worlds.truncate(self.index + 1);
+        self.worlds.push(world);
+        self.index = self.worlds.len() - 1;
+    }
+
+    pub fn back(&mut self) -> Option<WorldCode> {
+        self.index = self.index.checked_sub(1)?;
+        Some(self.worlds[self.index])
+    }
+
+    pub fn forward(&mut self) -> Option<WorldCode> {
+        let world = *self.worlds.get(self.index + 1)?;
+        self.index += 1;
+        Some(world)
+    }
+}
+
+impl SeedBookmarks {
+    /// A missing file means no bookmarks yet, unreadable codes are skipped
+    pub fn load(path: &str) -> Self {
+        let codes = match std::fs::read_to_string(path) {
+            Ok(contents) => ron::de::from_str::<Vec<String>>(&contents)
+                .map_err(|e| warn!("invalid bookmarks file {path}: {e}"))
+                .unwrap_or_default(),
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                warn!("could not read bookmarks file {path}: {e}");
+                Vec::new()
+            }
+        };
+
+        Self {
+            path: path.to_string(),
+            codes: codes
+                .iter()
+                .filter_map(|code| WorldCode::decode(code))
+                .collect(),
+        }
+    }
+
+    pub fn save(&self) {
+        let codes = self
+            .codes
+            .iter()
+            .map(|code| code.encode())
+            .collect::<Vec<_>>();
+        let result = ron::ser::to_string_pretty(&codes, default())
+            .map_err(|e| e.to_string())
+            .and_then(|contents| std::fs::write(&self.path, contents).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("could not save bookmarks to {}: {e}", self.path);
+        }
+    }
+
+    pub fn contains(&self, world: WorldCode) -> bool {
+        self.codes.iter().any(|code| code.same_world(&world))
+    }
+
+    /// Adds the world or removes it when it was already bookmarked
+    pub fn toggle(&mut self, world: WorldCode) {
+        if self.contains(world) {
+            self.codes.retain(|code| !code.same_world(&world));
+        } else {
+            self.codes.push(world);
+        }
+    }
+
+    /// Bookmark following the current world, wrapping around
+    pub fn next(&self, world: WorldCode) -> Option<WorldCode> {
+        let index = self.codes.iter().position(|code| code.same_world(&world));
+        let next = index.map_or(0, |i| (i + 1) % self.codes.len());
+        self.codes.get(next).copied()
+    }
+}
+
+/// World typed by a player, a number is used as the seed as is, world codes keep
+/// the generator they were made with, even one this build doesn't have, anything else is hashed
+pub fn parse_seed(text: &str) -> Option<WorldCode> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Ok(seed) = text.parse::<u32>() {
+        return Some(WorldCode::new(seed));
+    }
+    if let Some(code) = WorldCode::decode(text) {
+        return Some(code);
+    }
+
+    Some(WorldCode::new(hash_seed(text)))
+}
+
+pub fn hash_seed(text: &str) -> u32 {
+    fnv1a(text.as_bytes())
+}
+
+fn spawn_seed_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+        SeedText,
+    ));
+}
+
+fn handle_seed_input(
+    mut input: ResMut<SeedInput>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut writer: EventWriter<ResetTerrainEvent>,
+) {
+    if !input.active {
+        chars.clear();
+        if keys.just_pressed(KeyCode::Return) {
+            input.active = true;
+            input.text.clear();
+            keys.reset_all();
+        }
+        return;
+    }
+
+    for ev in chars.read() {
+        if !ev.char.is_control() {
+            input.text.push(ev.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        input.text.pop();
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        input.active = false;
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        input.active = false;
+        if let Some(world) = parse_seed(&input.text) {
+            writer.send(ResetTerrainEvent(Some(world)));
+        }
+    }
+
+    // Typing shouldn't move the player, trigger shortcuts or close the game
+    keys.reset_all();
+}
+
+fn handle_seed_keys(
+    keys: Res<Input<KeyCode>>,
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    gen_config: Res<ActiveGenerationConfig>,
+    mut history: ResMut<SeedHistory>,
+    mut bookmarks: ResMut<SeedBookmarks>,
+    mut writer: EventWriter<ResetTerrainEvent>,
+) {
+    let world = current_world(&seed, &version, &gen_config);
+    let target = if keys.just_pressed(KeyCode::BracketLeft) {
+        history.back()
+    } else if keys.just_pressed(KeyCode::BracketRight) {
+        history.forward()
+    } else if keys.just_pressed(KeyCode::N) {
+        bookmarks.next(world)
+    } else {
+        None
+    };
+    if let Some(target) = target.filter(|target| !target.same_world(&world)) {
+        writer.send(ResetTerrainEvent(Some(target)));
+    }
+
+    if keys.just_pressed(KeyCode::B) {
+        bookmarks.toggle(world);
+        bookmarks.save();
+    }
+}
+
+/// Worlds are recorded once applied, whatever reset them
+fn record_seed_history(
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    gen_config: Res<ActiveGenerationConfig>,
+    mut history: ResMut<SeedHistory>,
+) {
+    if seed.is_changed() || version.is_changed() {
+        history.visit(current_world(&seed, &version, &gen_config));
+    }
+}
+
+fn current_world(
+    seed: &GenerationSeed,
+    version: &GeneratorVersion,
+    gen_config: &ActiveGenerationConfig,
+) -> WorldCode {
+    WorldCode {
+        version: version.0,
+        seed: seed.0,
+        config: Some(gen_config.digest),
+    }
+}
+
+fn update_seed_text(
+    seed: Res<GenerationSeed>,
+    version: Res<GeneratorVersion>,
+    missing: Res<MissingGenerator>,
+    gen_config: Res<ActiveGenerationConfig>,
+    input: Res<SeedInput>,
+    history: Res<SeedHistory>,
+    bookmarks: Res<SeedBookmarks>,
+    mut text_query: Query<&mut Text, With<SeedText>>,
+) {
+    if !seed.is_changed()
+        && !version.is_changed()
+        && !missing.is_changed()
+        && !gen_config.is_changed()
+        && !input.is_changed()
+        && !history.is_changed()
+        && !bookmarks.is_changed()
+    {
+        return;
+    }
+
+    let world = current_world(&seed, &version, &gen_config);
+    let value = if input.active {
+        format!("Seed: {}_", input.text)
+    } else {
+        format!(
+            "Seed {}{}  Code {}  Generator v{}{}  World {}/{}",
+            seed.0,
+            if bookmarks.contains(world) { " *" } else { "" },
+            world.encode(),
+            version.0,
+            match missing.0 {
+                Some(wanted) => format!(" (v{wanted} unavailable)"),
+                None => String::new(),
+            },
+            history.index + 1,
+            history.worlds.len(),
+        )
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::ErrorKind;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::ron_loader::RonLoaderError;
+use crate::*;
+
+/// Contents of the settings file, read once at startup before the app is built.
+/// Missing fields keep the defaults from `configs.rs`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub world: WorldSettings,
+    pub player: PlayerSettings,
+}
+
+/// Window, streaming and level of detail tunables, inserted by `TerrainPlugin`
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WorldSettings {
+    pub window_size: (usize, usize),
+    /// Takes priority over the palette's water color when set
+    pub bg_color: Option<(u8, u8, u8)>,
+    /// Tiles per chunk
+    pub chunk_size: (usize, usize),
+    pub chunk_cache_budget_mb: usize,
+    pub max_camera_chunk_span: i32,
+    pub max_chunk_gen_tasks: usize,
+    pub tile_spawn_budget: usize,
+    pub tile_spawn_time_budget_ms: f32,
+    pub prefetch_lookahead_secs: f32,
+    pub prefetch_steps: usize,
+    pub tile_animation_interval: f32,
+    pub lod_sprite_max_scale: f32,
+    pub lod_coarse_min_scale: f32,
+    pub lod_tile_radius: u32,
+    pub lod_coarse_tiles: usize,
+    pub max_lod_chunk_span: i32,
+    /// Level of detail textures sampled at once, off the main thread
+    pub lod_chunks_per_frame: usize,
+    /// Level of detail colors before the palette tint, like the sprite sheet colors
+    pub lod_ground_color: (u8, u8, u8),
+    pub lod_forest_color: (u8, u8, u8),
+    pub lod_dense_forest_color: (u8, u8, u8),
+}
+
+/// Movement and trail tunables, inserted by `PlayerPlugin`
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PlayerSettings {
+    pub speed: f32,
+    pub fish_speed: f32,
+    pub animation_interval: f32,
+    pub walk_trail_interval: f32,
+    pub trail_life_span: f32,
+    pub jump_time: f32,
+    /// The player spawns on the nearest island to this world position
+    pub spawn_point: (f32, f32),
+    pub spawn_min_island_tiles: usize,
+    pub spawn_search_radius: i32,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid settings file {path}: {source}")]
+pub struct SettingsError {
+    path: String,
+    source: RonLoaderError,
+}
+
+impl Settings {
+    /// Reads a RON settings file, a missing file means default settings
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let path = path.as_ref();
+        let error = |source: RonLoaderError| SettingsError {
+            path: path.display().to_string(),
+            source,
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(contents) => ron::de::from_str(&contents).map_err(|e| error(e.into())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(error(e.into())),
+        }
+    }
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            window_size: (WW, WH),
+            bg_color: BG_COLOR,
+            chunk_size: (CHUNK_W, CHUNK_H),
+            chunk_cache_budget_mb: CHUNK_CACHE_BUDGET_MB,
+            max_camera_chunk_span: MAX_CAMERA_CHUNK_SPAN,
+            max_chunk_gen_tasks: MAX_CHUNK_GEN_TASKS,
+            tile_spawn_budget: TILE_SPAWN_BUDGET,
+            tile_spawn_time_budget_ms: TILE_SPAWN_TIME_BUDGET_MS,
+            prefetch_lookahead_secs: PREFETCH_LOOKAHEAD_SECS,
+            prefetch_steps: PREFETCH_STEPS,
+            tile_animation_interval: TILE_ANIMATION_INTERVAL,
+            lod_sprite_max_scale: LOD_SPRITE_MAX_SCALE,
+            lod_coarse_min_scale: LOD_COARSE_MIN_SCALE,
+            lod_tile_radius: LOD_TILE_RADIUS,
+            lod_coarse_tiles: LOD_COARSE_TILES,
+            max_lod_chunk_span: MAX_LOD_CHUNK_SPAN,
+            lod_chunks_per_frame: LOD_CHUNKS_PER_FRAME,
+            lod_ground_color: LOD_GROUND_COLOR,
+            lod_forest_color: LOD_FOREST_COLOR,
+            lod_dense_forest_color: LOD_DENSE_FOREST_COLOR,
+        }
+    }
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            speed: PLAYER_SPEED,
+            fish_speed: PLAYER_FISH_SPEED,
+            animation_interval: PLAYER_ANIMATION_INTERVAL,
+            walk_trail_interval: WALK_TRAIL_TIMER,
+            trail_life_span: TRAIL_LIFE_SPAN,
+            jump_time: PLAYER_JUMP_TIME,
+            spawn_point: PLAYER_SPAWN_POINT,
+            spawn_min_island_tiles: SPAWN_MIN_ISLAND_TILES,
+            spawn_search_radius: SPAWN_SEARCH_RADIUS,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::cmp::Reverse;
+
+use bevy::utils::{HashMap, HashSet};
+
+use crate::generation::GenerationConfig;
+use crate::generator::ChunkGenFn;
+use crate::macro_map::MacroMap;
+use crate::terrain::ChunkData;
+
+/// Where to look for a spawn point and what counts as a good one
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnSearch {
+    /// Terrain grid tile the search starts from
+    pub start: (i32, i32),
+    /// Smallest island the player may spawn on, in walkable tiles
+    pub min_island_tiles: usize,
+    /// Chunk rings searched around the start before giving up
+    pub max_radius: i32,
+}
+
+/// Spawn point found by a search, along with every chunk it generated on the way
+pub struct SpawnSearchResult {
+    pub spawn: Option<(i32, i32)>,
+    /// Farthest from the start first
+    pub chunks: Vec<ChunkData>,
+}
+
+/// Generates chunks on demand to look up walkable ground across chunk borders
+struct GroundLookup<'a> {
+    gen_chunk: ChunkGenFn,
+    gen_seed: u32,
+    chunk_size: (usize, usize),
+    config: &'a GenerationConfig,
+    macro_map: &'a MacroMap,
+    chunks: HashMap<(i32, i32), ChunkData>,
+}
+
+/// Nearest walkable tile to the start on an island of at least `min_island_tiles`,
+/// chunks are searched ring by ring outwards and generated the same way the terrain is.
+/// The generated chunks are handed back so the terrain doesn't generate them again
+pub fn find_spawn_point(
+    gen_chunk: ChunkGenFn,
+    gen_seed: u32,
+    chunk_size: (usize, usize),
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+    search: SpawnSearch,
+) -> SpawnSearchResult {
+    let mut lookup = GroundLookup {
+        gen_chunk,
+        gen_seed,
+        chunk_size,
+        config,
+        macro_map,
+        chunks: HashMap::new(),
+    };
+    let spawn = lookup.search(search);
+    let start = lookup.chunk_of(search.start);
+    let mut chunks = lookup.chunks.into_values().collect::<Vec<_>>();
+    chunks.sort_by_key(|chunk| {
+        let (x, y) = chunk.pos;
+        Reverse(x.abs_diff(start.0).max(y.abs_diff(start.1)))
+    });
+
+    SpawnSearchResult { spawn, chunks }
+}
+
+impl GroundLookup<'_> {
+    fn search(&mut self, search: SpawnSearch) -> Option<(i32, i32)> {
+        let (sx, sy) = search.start;
+        let (cx, cy) = self.chunk_of(search.start);
+
+        // Tiles of islands already found too small
+        let mut rejected = HashSet::new();
+        for r in 0..=search.max_radius {
+            let mut candidates = Vec::new();
+            for x in cx - r..=cx + r {
+                for y in cy - r..=cy + r {
+                    if (x - cx).abs().max((y - cy).abs()) != r {
+                        continue;
+                    }
+                    candidates.extend(self.chunk((x, y)).ground_tiles());
+                }
+            }
+            candidates.sort_by_key(|(x, y)| {
+                let (dx, dy) = ((x - sx) as i64, (y - sy) as i64);
+                dx * dx + dy * dy
+            });
+
+            for tile in candidates {
+                if rejected.contains(&tile) {
+                    continue;
+                }
+                let island = self.island(tile, search.min_island_tiles);
+                if island.len() >= search.min_island_tiles {
+                    return Some(tile);
+                }
+                rejected.extend(island);
+            }
+        }
+
+        None
+    }
+
+    fn chunk_of(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (w, h) = self.chunk_size;
+        (x.div_euclid(w as i32), y.div_euclid(h as i32))
+    }
+
+    fn chunk(&mut self, pos: (i32, i32)) -> &ChunkData {
+        self.chunks.entry(pos).or_insert_with(|| {
+            (self.gen_chunk)(
+                self.gen_seed,
+                pos,
+                self.chunk_size,
+                self.config,
+                self.macro_map,
+            )
+        })
+    }
+
+    fn is_ground(&mut self, tile: (i32, i32)) -> bool {
+        let pos = self.chunk_of(tile);
+        self.chunk(pos).ground.get(tile) == Some(true)
+    }
+
+    /// Walkable tiles connected to `start`, the flood fill stops once `limit` tiles are found
+    fn island(&mut self, start: (i32, i32), limit: usize) -> HashSet<(i32, i32)> {
+        let mut island = HashSet::from([start]);
+        let mut open = vec![start];
+        while let Some((x, y)) = open.pop() {
+            for nei in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if island.len() >= limit {
+                    return island;
+                }
+                if !island.contains(&nei) && self.is_ground(nei) {
+                    island.insert(nei);
+                    open.push(nei);
+                }
+            }
+        }
+
+        island
+    }
+}
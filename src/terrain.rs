@@ -1,13 +1,32 @@
+use std::sync::Arc;
 use std::time::Duration;
 
-use bevy::math::{vec2, vec3};
+use bevy::diagnostic::Diagnostics;
+use bevy::math::vec3;
 use bevy::prelude::*;
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
 use bevy::time::common_conditions::on_timer;
-use bevy::utils::{HashMap, HashSet};
+use bevy::utils::{HashMap, HashSet, Instant};
 use noise::{NoiseFn, Perlin};
-use rand::Rng;
-
-use crate::player::{CurrentPlayerChunkPos, PlayerChunkUpdateEvent};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::chunk_cache::ChunkCache;
+use crate::chunk_gen::*;
+use crate::chunk_grid::ChunkGrid;
+use crate::coastline::{coast_roughness, smooth_coastline, warp};
+use crate::diagnostics::TerrainDiagnosticsPlugin;
+use crate::draw_order::{DrawLayer, YSorted};
+use crate::erosion::erosion_grid;
+use crate::generation::{ActiveGenerationConfig, GenerationConfig, GenerationConfigChangedEvent};
+use crate::generator::WorldCode;
+use crate::macro_map::{update_macro_map, ActiveMacroMap, MacroMap};
+use crate::names::{chunk_places, Place};
+use crate::palette::{PaletteChangedEvent, PaletteThemes};
+use crate::player::CurrentPlayerChunkPos;
+use crate::settings::WorldSettings;
+use crate::tile_animation::AnimatedTile;
+use crate::tileset::{TilesetAtlas, TilesetChangedEvent};
 use crate::utils::*;
 use crate::*;
 
@@ -16,276 +35,750 @@ struct TileComponent;
 #[derive(Resource)]
 pub struct GroundTiles(pub HashSet<(i32, i32)>);
 #[derive(Resource)]
-struct CurrentChunks(HashMap<(i32, i32), Vec<Entity>>);
+pub(crate) struct CurrentChunks(pub(crate) HashMap<(i32, i32), SpawnedChunk>);
+#[derive(Resource)]
+pub struct GenerationSeed(pub u32);
+/// Version of the generator the current world is made with, see `generator::GENERATORS`
 #[derive(Resource)]
-struct GenerationSeed(u32);
+pub struct GeneratorVersion(pub u8);
+/// Generator version the current world code asked for when this build doesn't have it,
+/// the world is made with `GENERATOR_VERSION` instead
+#[derive(Resource, Default)]
+pub struct MissingGenerator(pub Option<u8>);
+/// Chunks that should currently be spawned, around the player and every active camera
+#[derive(Resource, Default)]
+pub struct RequiredChunks(pub HashSet<(i32, i32)>);
+/// Regenerates the world from scratch, with a random seed and the latest generator when none is given
+#[derive(Event)]
+pub struct ResetTerrainEvent(pub Option<WorldCode>);
+/// A chunk finished generating and is now in the chunk cache
+#[derive(Event)]
+pub struct ChunkGeneratedEvent {
+    pub pos: (i32, i32),
+    pub data: Arc<ChunkData>,
+}
+/// All of a chunk's tile entities were spawned
+#[derive(Event)]
+pub struct ChunkSpawnedEvent {
+    pub pos: (i32, i32),
+    pub data: Arc<ChunkData>,
+}
+/// A chunk's tile entities were despawned, either streamed out or the world was reset
 #[derive(Event)]
-pub struct ResetTerrainEvent;
+pub struct ChunkUnloadedEvent {
+    pub pos: (i32, i32),
+    pub data: Arc<ChunkData>,
+}
 
-#[derive(Eq, PartialEq, Hash)]
-struct Tile {
-    pos: (i32, i32),
-    sprite: usize,
-    z_index: i32,
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Tile {
+    pub pos: (i32, i32),
+    /// Tile name in the tileset
+    pub name: &'static str,
+    pub variant: usize,
+    pub layer: DrawLayer,
+}
+
+/// Everything generated for a single chunk, ready to be spawned
+pub struct ChunkData {
+    pub pos: (i32, i32),
+    pub tiles: Vec<Tile>,
+    /// Walkable ground, shore edges excluded
+    pub ground: ChunkGrid<bool>,
+    pub biomes: ChunkGrid<BiomeSample>,
+    /// Named villages and mountains, named when the chunk is generated
+    pub places: Vec<Place>,
+}
+
+/// Noise values the biome thresholds are based on, used to blend biome colors
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BiomeSample {
+    pub elevation: f32,
+    pub forest: f32,
+}
+
+/// Tiles are spawned over multiple frames, the chunk is complete once every tile has an entity
+pub(crate) struct SpawnedChunk {
+    pub(crate) data: Arc<ChunkData>,
+    pub(crate) entities: Vec<Entity>,
+    /// Generated with an old config, stays on screen until its replacement is ready
+    pub(crate) stale: bool,
+}
+
+impl SpawnedChunk {
+    pub(crate) fn is_complete(&self) -> bool {
+        self.entities.len() == self.data.tiles.len()
+    }
 }
 
-pub struct TerrainPlugin;
+#[derive(Default)]
+pub struct TerrainPlugin {
+    pub settings: WorldSettings,
+    /// First world, a random seed with the latest generator when unset
+    pub world: Option<WorldCode>,
+}
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        let mut rng = rand::thread_rng();
+        let world = self
+            .world
+            .unwrap_or_else(|| WorldCode::new(rand::thread_rng().gen()));
         app.insert_resource(GroundTiles(HashSet::new()))
             .insert_resource(CurrentChunks(HashMap::new()))
-            .insert_resource(GenerationSeed(rng.gen()))
+            .insert_resource(ChunkCache::new(
+                self.settings.chunk_cache_budget_mb * 1024 * 1024,
+            ))
+            .insert_resource(self.settings.clone())
+            .insert_resource(GenerationSeed(world.seed))
+            .insert_resource(GeneratorVersion(world_generator(world)))
+            .insert_resource(MissingGenerator(missing_generator(world)))
+            .insert_resource(RequiredChunks::default())
+            .insert_resource(PrefetchChunks::default())
+            .insert_resource(ChunkGenTasks::default())
+            .init_resource::<ActiveMacroMap>()
             .add_systems(Update, handle_terrain_reset_event)
-            .add_systems(Update, despawn_chunks)
+            .add_systems(
+                Update,
+                warn_missing_generator.after(handle_terrain_reset_event),
+            )
+            .add_systems(
+                Update,
+                handle_generation_config_change.after(handle_terrain_reset_event),
+            )
+            .add_systems(
+                Update,
+                update_macro_map.after(handle_generation_config_change),
+            )
+            .add_systems(
+                Update,
+                refresh_tile_sprites.run_if(
+                    on_event::<TilesetChangedEvent>().or_else(on_event::<PaletteChangedEvent>()),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_required_chunks,
+                    prefetch_chunks,
+                    queue_chunk_generation,
+                    poll_chunk_generation,
+                    despawn_chunks,
+                    queue_chunk_spawns,
+                    spawn_queued_tiles,
+                )
+                    .chain()
+                    .after(update_macro_map),
+            )
             .add_systems(
                 Update,
                 clean_ground_tiles.run_if(on_timer(Duration::from_secs_f32(2.0))),
             )
-            .add_systems(Update, handle_player_chunk_update_event)
-            .add_event::<ResetTerrainEvent>();
+            .add_event::<ResetTerrainEvent>()
+            .add_event::<ChunkGeneratedEvent>()
+            .add_event::<ChunkSpawnedEvent>()
+            .add_event::<ChunkUnloadedEvent>();
     }
 }
 
 fn handle_terrain_reset_event(
     mut commands: Commands,
     mut reader: EventReader<ResetTerrainEvent>,
-    mut ev_writer: EventWriter<PlayerChunkUpdateEvent>,
-    player_pos: Res<CurrentPlayerChunkPos>,
+    mut ev_unloaded: EventWriter<ChunkUnloadedEvent>,
     mut chunks: ResMut<CurrentChunks>,
+    mut chunk_cache: ResMut<ChunkCache>,
+    mut gen_tasks: ResMut<ChunkGenTasks>,
     mut ground_tiles: ResMut<GroundTiles>,
     mut seed: ResMut<GenerationSeed>,
+    mut version: ResMut<GeneratorVersion>,
+    mut missing: ResMut<MissingGenerator>,
+    gen_config: Res<ActiveGenerationConfig>,
     tile_q: Query<Entity, With<TileComponent>>,
 ) {
-    if reader.is_empty() {
+    let Some(new_world) = reader.read().last().map(|ev| ev.0) else {
         return;
-    }
+    };
 
-    reader.clear();
     for t in tile_q.iter() {
         commands.entity(t).despawn();
     }
 
     // Reset res
-    chunks.0.clear();
+    for (pos, chunk) in chunks.0.drain() {
+        if !chunk.is_complete() {
+            continue;
+        }
+        ev_unloaded.send(ChunkUnloadedEvent {
+            pos,
+            data: chunk.data,
+        });
+    }
+    chunk_cache.clear();
+    gen_tasks.0.clear();
     ground_tiles.0.clear();
 
-    let mut rng = rand::thread_rng();
-    seed.0 = rng.gen();
+    // The world re-generates as the required chunks are now all missing
+    let world = new_world.unwrap_or_else(|| WorldCode::new(rand::thread_rng().gen()));
+    if world
+        .config
+        .is_some_and(|config| config != gen_config.digest)
+    {
+        warn!(
+            "world {} was made with other generation settings, it won't look the same",
+            world.encode()
+        );
+    }
+    seed.0 = world.seed;
+    version.0 = world_generator(world);
+    missing.0 = missing_generator(world);
+}
 
-    // Trigger world re-generation
-    let (x, y) = player_pos.0;
-    ev_writer.send(PlayerChunkUpdateEvent((x, y)));
+/// Version of the generator a world is made with, the latest one when the code's is unknown
+fn world_generator(world: WorldCode) -> u8 {
+    world.generator().map_or(GENERATOR_VERSION, |g| g.version)
+}
+
+fn missing_generator(world: WorldCode) -> Option<u8> {
+    world.generator().is_none().then_some(world.version)
+}
+
+fn warn_missing_generator(missing: Res<MissingGenerator>, seed: Res<GenerationSeed>) {
+    if !missing.is_changed() {
+        return;
+    }
+    if let Some(version) = missing.0 {
+        warn!(
+            "world {} needs generator v{version}, using v{GENERATOR_VERSION}",
+            seed.0
+        );
+    }
+}
+
+/// Loaded chunks are regenerated with the same seed and swapped in one by one,
+/// cached chunks of the old config are dropped
+fn handle_generation_config_change(
+    mut reader: EventReader<GenerationConfigChangedEvent>,
+    mut chunks: ResMut<CurrentChunks>,
+    mut chunk_cache: ResMut<ChunkCache>,
+    mut gen_tasks: ResMut<ChunkGenTasks>,
+) {
+    if reader.is_empty() {
+        return;
+    }
+
+    reader.clear();
+    for chunk in chunks.0.values_mut() {
+        chunk.stale = true;
+    }
+    chunk_cache.clear();
+    gen_tasks.0.clear();
 }
 
+/// Tile entities follow `ChunkData::tiles` order, so sprites can be looked up again in place
+fn refresh_tile_sprites(
+    mut commands: Commands,
+    tileset: Res<TilesetAtlas>,
+    palette: Res<PaletteThemes>,
+    gen_config: Res<ActiveGenerationConfig>,
+    current_chunks: Res<CurrentChunks>,
+    mut sprite_query: Query<&mut TextureAtlasSprite, With<TileComponent>>,
+) {
+    for (pos, chunk) in current_chunks.0.iter() {
+        for (e, t) in chunk.entities.iter().zip(chunk.data.tiles.iter()) {
+            if let Ok(mut sprite) = sprite_query.get_mut(*e) {
+                *sprite = tile_sprite(&tileset, &palette, &gen_config.config, &chunk.data, t);
+            }
+
+            // Animations may have been added or removed by the new tileset
+            if tileset.is_animated(t.name) {
+                commands.entity(*e).insert(AnimatedTile::new(*pos, t));
+            } else {
+                commands.entity(*e).remove::<AnimatedTile>();
+            }
+        }
+    }
+}
+
+/// Drops ground left behind by chunks that are no longer loaded,
+/// anything the player or a camera has loaded keeps its ground
 fn clean_ground_tiles(
+    settings: Res<WorldSettings>,
     player_pos: Res<CurrentPlayerChunkPos>,
+    current_chunks: Res<CurrentChunks>,
     mut ground_tiles: ResMut<GroundTiles>,
 ) {
     let (x, y) = player_pos.0;
     ground_tiles.0.retain(|pos| {
-        let (px, py) = grid_to_chunk(pos.0 as f32, pos.1 as f32);
-        px.abs_diff(x) <= 1 || py.abs_diff(y) <= 1
+        let (px, py) = grid_to_chunk(pos.0 as f32, pos.1 as f32, settings.chunk_size);
+        current_chunks.0.contains_key(&(px, py)) || (px.abs_diff(x) <= 1 && py.abs_diff(y) <= 1)
     });
 }
 
+pub(crate) fn update_required_chunks(
+    settings: Res<WorldSettings>,
+    player_pos: Res<CurrentPlayerChunkPos>,
+    cam_query: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut required: ResMut<RequiredChunks>,
+) {
+    let mut chunks = HashSet::new();
+    let (x, y) = player_pos.0;
+    for i in -1..=1 {
+        for j in -1..=1 {
+            chunks.insert((x + i, y + j));
+        }
+    }
+
+    for (camera, transform, projection) in cam_query.iter() {
+        // Zoomed out cameras are covered by the lod textures instead
+        if !camera.is_active || projection.scale > settings.lod_sprite_max_scale {
+            continue;
+        }
+
+        // The visible area already accounts for the zoom level
+        let center = transform.translation().truncate();
+        let ((x1, y1), (x2, y2)) =
+            visible_terrain_chunks(center, projection.area, settings.chunk_size);
+        let (cx, cy) = world_to_terrain_chunk(center.x, center.y, settings.chunk_size);
+
+        // Way zoomed out views are clamped around the center instead of loading everything
+        let span = settings.max_camera_chunk_span;
+        let (x1, x2) = ((x1 - 1).max(cx - span), (x2 + 1).min(cx + span));
+        let (y1, y2) = ((y1 - 1).max(cy - span), (y2 + 1).min(cy + span));
+        for i in x1..=x2 {
+            for j in y1..=y2 {
+                chunks.insert((i, j));
+            }
+        }
+    }
+
+    if chunks != required.0 {
+        required.0 = chunks;
+    }
+}
+
 fn despawn_chunks(
     mut commands: Commands,
     mut current_chunks: ResMut<CurrentChunks>,
-    player_pos: Res<CurrentPlayerChunkPos>,
+    mut ground_tiles: ResMut<GroundTiles>,
+    mut ev_unloaded: EventWriter<ChunkUnloadedEvent>,
+    required: Res<RequiredChunks>,
 ) {
     let mut keys_to_remove = Vec::new();
-    let (x, y) = player_pos.0;
 
-    for ((cx, cy), entities) in current_chunks.0.iter() {
-        if cx.abs_diff(x) <= 1 && cy.abs_diff(y) <= 1 {
+    for ((cx, cy), chunk) in current_chunks.0.iter() {
+        if required.0.contains(&(*cx, *cy)) {
             continue;
         }
 
-        for e in entities.iter() {
+        for e in chunk.entities.iter() {
             commands.entity(*e).despawn();
         }
         keys_to_remove.push((*cx, *cy));
     }
 
     for (cx, cy) in keys_to_remove {
-        current_chunks.0.remove(&(cx, cy));
+        let Some(chunk) = current_chunks.0.remove(&(cx, cy)) else {
+            continue;
+        };
+        for tile in chunk.data.ground_tiles() {
+            ground_tiles.0.remove(&tile);
+        }
+        if chunk.is_complete() {
+            ev_unloaded.send(ChunkUnloadedEvent {
+                pos: (cx, cy),
+                data: chunk.data,
+            });
+        }
     }
 }
 
-fn handle_player_chunk_update_event(
+fn queue_chunk_spawns(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    seed: Res<GenerationSeed>,
+    required: Res<RequiredChunks>,
     mut current_chunks: ResMut<CurrentChunks>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut ev_chunk_update: EventReader<PlayerChunkUpdateEvent>,
+    mut chunk_cache: ResMut<ChunkCache>,
     mut ground_tiles: ResMut<GroundTiles>,
+    mut ev_spawned: EventWriter<ChunkSpawnedEvent>,
+    mut ev_unloaded: EventWriter<ChunkUnloadedEvent>,
+) {
+    let missing = required
+        .0
+        .iter()
+        .filter(|pos| {
+            current_chunks.0.get(*pos).is_none_or(|chunk| chunk.stale)
+                && chunk_cache.contains(**pos)
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    for pos in missing {
+        // Chunks show up here once generated, revisited ones straight from the cache
+        let Some(chunk) = chunk_cache.get(pos) else {
+            continue;
+        };
+
+        // Regenerated chunks replace the old tiles in place
+        if let Some(old) = current_chunks.0.remove(&pos) {
+            for e in old.entities.iter() {
+                commands.entity(*e).despawn();
+            }
+            for tile in old.data.ground_tiles() {
+                ground_tiles.0.remove(&tile);
+            }
+            if old.is_complete() {
+                ev_unloaded.send(ChunkUnloadedEvent {
+                    pos,
+                    data: old.data,
+                });
+            }
+        }
+        ground_tiles.0.extend(chunk.ground_tiles());
+
+        // Open ocean chunks have nothing to spawn
+        if chunk.tiles.is_empty() {
+            ev_spawned.send(ChunkSpawnedEvent {
+                pos,
+                data: chunk.clone(),
+            });
+        }
+        current_chunks.0.insert(
+            pos,
+            SpawnedChunk {
+                entities: Vec::with_capacity(chunk.tiles.len()),
+                data: chunk,
+                stale: false,
+            },
+        );
+    }
+}
+
+fn spawn_queued_tiles(
+    mut commands: Commands,
+    settings: Res<WorldSettings>,
+    tileset: Res<TilesetAtlas>,
+    palette: Res<PaletteThemes>,
+    gen_config: Res<ActiveGenerationConfig>,
+    player_pos: Res<CurrentPlayerChunkPos>,
+    mut current_chunks: ResMut<CurrentChunks>,
+    mut ev_spawned: EventWriter<ChunkSpawnedEvent>,
+    mut diagnostics: Diagnostics,
 ) {
-    if ev_chunk_update.is_empty() {
+    // Sprite indices come from the tileset
+    if !tileset.is_loaded() {
         return;
     }
 
-    let texture_handle = asset_server.load(SPRITE_SHEET_PATH);
-    let texture_atlas = TextureAtlas::from_grid(
-        texture_handle,
-        vec2(TILE_W as f32, TILE_H as f32),
-        SPRITE_SHEET_W,
-        SPRITE_SHEET_H,
-        Some(Vec2::splat(SPRITE_PADDING)),
-        Some(Vec2::splat(SPRITE_SHEET_OFFSET)),
-    );
-    let handle = texture_atlases.add(texture_atlas);
-
-    for new_chunk_pos in ev_chunk_update.read() {
-        let (x, y) = new_chunk_pos.0;
-
-        let chunk_nei = [
-            (-1, 0),
-            (1, 0),
-            (0, -1),
-            (0, 1),
-            (-1, 1),
-            (1, 1),
-            (-1, -1),
-            (1, -1),
-            (0, 0),
-        ];
-        let mut tiles = HashSet::new();
-        let mut ground_map = HashSet::new();
-
-        for (i, j) in chunk_nei.iter() {
-            let (x, y) = (x + *i, y + *j);
-            if current_chunks.0.contains_key(&(x, y)) {
-                continue;
+    // Nearest chunks drain first
+    let (px, py) = player_pos.0;
+    let mut queue = current_chunks
+        .0
+        .iter()
+        .filter(|(_, chunk)| !chunk.is_complete() && !chunk.stale)
+        .map(|(pos, _)| *pos)
+        .collect::<Vec<_>>();
+    queue.sort_by_key(|(x, y)| x.abs_diff(px).max(y.abs_diff(py)));
+
+    let started_at = Instant::now();
+    let mut budget = settings.tile_spawn_budget;
+    for pos in queue.iter() {
+        let Some(chunk) = current_chunks.0.get_mut(pos) else {
+            continue;
+        };
+
+        let start = chunk.entities.len();
+        for t in chunk.data.tiles[start..].iter() {
+            if budget == 0 {
+                break;
             }
+            budget -= 1;
 
-            let start = (x * CHUNK_W as i32, y * CHUNK_H as i32);
-            let (chunk_tiles, chunk_ground_map) = gen_chunk(seed.0, (start.0, start.1));
-            tiles.extend(chunk_tiles);
-            ground_map.extend(chunk_ground_map);
+            let (x, y) = grid_to_world(t.pos.0 as f32, t.pos.1 as f32);
+            let (x, y) = center_to_top_left(x, y);
+            let mut e = commands.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: tileset.atlas.clone(),
+                    sprite: tile_sprite(&tileset, &palette, &gen_config.config, &chunk.data, t),
+                    transform: Transform::from_scale(Vec3::splat(SPRITE_SCALE_FACTOR as f32))
+                        .with_translation(vec3(x, y, t.layer.z())),
+                    ..default()
+                },
+                TileComponent,
+            ));
+            if t.layer.is_y_sorted() {
+                e.insert(YSorted(t.layer));
+            }
+            if tileset.is_animated(t.name) {
+                e.insert(AnimatedTile::new(*pos, t));
+            }
+            chunk.entities.push(e.id());
         }
 
-        let mut updated_ground_map = HashSet::new();
-        for (x, y) in ground_map.iter() {
-            let (num_nei, tile) = process_tile((*x, *y), &ground_map);
-            if num_nei == 1 {
-                continue;
-            }
+        if chunk.is_complete() {
+            ev_spawned.send(ChunkSpawnedEvent {
+                pos: *pos,
+                data: chunk.data.clone(),
+            });
+        }
 
-            // Ignore edges
-            // This will help in better player visualization when going from land to water
-            updated_ground_map.insert((*x, *y));
-            tiles.insert(Tile::new((*x, *y), tile, 0));
+        // Time is only checked between chunks, the entity budget bounds a single chunk
+        if budget == 0
+            || started_at.elapsed().as_secs_f32() * 1000.0 > settings.tile_spawn_time_budget_ms
+        {
+            break;
         }
-        ground_tiles.0.extend(updated_ground_map);
+    }
 
-        for t in tiles.iter() {
-            let (cx, cy) = grid_to_chunk(t.pos.0 as f32, t.pos.1 as f32);
-            let (x, y) = grid_to_world(t.pos.0 as f32, t.pos.1 as f32);
-            let (x, y) = center_to_top_left(x, y);
+    let pending = current_chunks
+        .0
+        .values()
+        .filter(|chunk| !chunk.is_complete())
+        .map(|chunk| chunk.data.tiles.len() - chunk.entities.len())
+        .collect::<Vec<_>>();
+    let spawned = settings.tile_spawn_budget - budget;
+    diagnostics.add_measurement(TerrainDiagnosticsPlugin::TILES_SPAWNED, || spawned as f64);
+    diagnostics.add_measurement(TerrainDiagnosticsPlugin::SPAWN_QUEUE_CHUNKS, || {
+        pending.len() as f64
+    });
+    diagnostics.add_measurement(TerrainDiagnosticsPlugin::SPAWN_QUEUE_TILES, || {
+        pending.iter().sum::<usize>() as f64
+    });
+}
 
-            let e = commands
-                .spawn((
-                    SpriteSheetBundle {
-                        texture_atlas: handle.clone(),
-                        sprite: TextureAtlasSprite::new(t.sprite),
-                        transform: Transform::from_scale(Vec3::splat(SPRITE_SCALE_FACTOR as f32))
-                            .with_translation(vec3(x, y, t.z_index as f32)),
-                        ..default()
-                    },
-                    TileComponent,
-                ))
-                .id();
-
-            current_chunks
-                .0
-                .entry((cx, cy))
-                .or_insert_with(Vec::new)
-                .push(e);
-        }
+/// Sheet sprite of a tile, tinted by the biome it stands on
+fn tile_sprite(
+    tileset: &TilesetAtlas,
+    palette: &PaletteThemes,
+    config: &GenerationConfig,
+    chunk: &ChunkData,
+    t: &Tile,
+) -> TextureAtlasSprite {
+    let biome = chunk.biomes.get(t.pos).unwrap_or_default();
+    TextureAtlasSprite {
+        color: palette.tint(t.name, biome, config),
+        ..tileset.sprite(t.name, t.variant)
     }
 }
 
-fn gen_chunk(gen_seed: u32, start: (i32, i32)) -> (HashSet<Tile>, HashSet<(i32, i32)>) {
-    let mut rng = rand::thread_rng();
+/// First generator, registered as version 1 in `generator::GENERATORS`, its algorithm must not
+/// change, the world it generates still follows the generation config a world code records
+pub fn gen_chunk_v1(
+    gen_seed: u32,
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+    _macro_map: &MacroMap,
+) -> ChunkData {
+    let noise = Perlin::new(gen_seed);
+    let start = (pos.0 * w as i32, pos.1 * h as i32);
+
+    // Sampled with a one tile border, the border is only used for autotiling
+    let samples = sample_noise_grid(&noise, config, (start.0 - 1, start.1 - 1), w + 2, h + 2);
+    let ground_map = samples.map(|(noise_val, _)| noise_val > config.sea_level);
+    decorate_chunk(gen_seed, pos, (w, h), config, &samples, &ground_map, true)
+}
+
+/// Tile noise raised into continents and sunk into open sea by the macro map
+pub fn gen_chunk_v2(
+    gen_seed: u32,
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+) -> ChunkData {
+    let noise = Perlin::new(gen_seed);
+    let start = (pos.0 * w as i32, pos.1 * h as i32);
+
+    let samples = sample_grid((start.0 - 1, start.1 - 1), w + 2, h + 2, |x, y| {
+        sample_noise_v2(&noise, config, macro_map, x, y)
+    });
+    let ground_map = samples.map(|(noise_val, _)| noise_val > config.sea_level);
+    decorate_chunk(gen_seed, pos, (w, h), config, &samples, &ground_map, true)
+}
+
+/// Warped and roughened coastlines, smoothed by cellular automata instead of
+/// dropping one tile edges
+pub fn gen_chunk_v3(
+    gen_seed: u32,
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+) -> ChunkData {
+    let noise = Perlin::new(gen_seed);
+    let (origin, sw, sh) = smoothing_area(pos, (w, h), config);
+    let samples = sample_grid(origin, sw, sh, |x, y| {
+        sample_noise_v3(&noise, config, macro_map, x, y)
+    });
+    decorate_smoothed_chunk(gen_seed, pos, (w, h), config, &samples)
+}
+
+/// Generator v3 terrain worn down by water droplets and sliding slopes,
+/// carving valleys and leaving talus at the foot of mountains
+pub fn gen_chunk_v4(
+    gen_seed: u32,
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+) -> ChunkData {
     let noise = Perlin::new(gen_seed);
+    let (origin, sw, sh) = smoothing_area(pos, (w, h), config);
+    let samples = sample_grid(origin, sw, sh, |x, y| {
+        sample_noise_v3(&noise, config, macro_map, x, y)
+    });
+    let erosion = erosion_grid(gen_seed, config, macro_map, origin, sw, sh);
+    let cells = samples
+        .iter()
+        .map(|(pos, (noise_val, octaves))| (noise_val + erosion.get(pos).unwrap(), octaves))
+        .collect();
+    let samples = ChunkGrid::from_cells(origin, sw, sh, cells);
+    decorate_smoothed_chunk(gen_seed, pos, (w, h), config, &samples)
+}
+
+/// Area sampled for a chunk with smoothed coastlines, every smoothing pass eats
+/// one tile of border and one more is left for autotiling
+fn smoothing_area(
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+) -> ((i32, i32), usize, usize) {
+    let border = config.smoothing_passes + 1;
+    let origin = (
+        pos.0 * w as i32 - border as i32,
+        pos.1 * h as i32 - border as i32,
+    );
+    (origin, w + border * 2, h + border * 2)
+}
+
+fn decorate_smoothed_chunk(
+    gen_seed: u32,
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+    samples: &ChunkGrid<(f64, [f64; 4])>,
+) -> ChunkData {
+    let land = samples.map(|(noise_val, _)| noise_val > config.sea_level);
+    let ground_map = smooth_coastline(&land, config.smoothing_passes);
+    decorate_chunk(gen_seed, pos, (w, h), config, samples, &ground_map, false)
+}
 
-    let mut tiles = HashSet::new();
-    let mut ground_map = HashSet::new();
-    let end = (start.0 + CHUNK_W as i32, start.1 + CHUNK_H as i32);
-    for x in start.0 - 1..end.0 + 1 {
-        for y in start.1 - 1..end.1 + 1 {
-            let noise_val1 = noise.get([x as f64 / 100.5, y as f64 / 100.5]);
-            let noise_val2 = noise.get([x as f64 / 53.5, y as f64 / 53.5]);
-            let noise_val3 = noise.get([x as f64 / 43.5, y as f64 / 43.5]);
-            let noise_val4 = noise.get([x as f64 / 23.5, y as f64 / 23.5]);
-            let noise_val = (noise_val1 + noise_val2 + noise_val3 + noise_val4) / 4.0;
+/// Ground, biomes and decorations of a chunk from its elevation and octave samples,
+/// `ground_map` is the land of the chunk with a one tile border around it
+fn decorate_chunk(
+    gen_seed: u32,
+    pos: (i32, i32),
+    (w, h): (usize, usize),
+    config: &GenerationConfig,
+    samples: &ChunkGrid<(f64, [f64; 4])>,
+    ground_map: &ChunkGrid<bool>,
+    prune_edges: bool,
+) -> ChunkData {
+    let mut rng = chunk_rng(gen_seed, pos);
+
+    let mut tiles = Vec::new();
+    let start = (pos.0 * w as i32, pos.1 * h as i32);
+    let end = (start.0 + w as i32, start.1 + h as i32);
+
+    let mut biomes = Vec::with_capacity(w * h);
+    for y in start.1..end.1 {
+        for x in start.0..end.0 {
+            let Some((noise_val, [_, _, noise_val3, noise_val4])) = samples.get((x, y)) else {
+                continue;
+            };
             let chance = rng.gen_range(0.0..1.0);
+            biomes.push(BiomeSample {
+                elevation: noise_val as f32,
+                forest: noise_val3 as f32,
+            });
 
             // Ground
-            if noise_val > 0.0 {
-                ground_map.insert((x, y));
-            } else {
+            if ground_map.get((x, y)) != Some(true) {
                 continue;
             }
 
             // Too close to shore
-            if noise_val < 0.05 {
+            if noise_val < config.shore_elevation {
                 continue;
             }
 
+            let in_range = |(min, max): (f64, f64)| noise_val > min && noise_val < max;
+            let is_dense = noise_val > config.dense_forest_elevation
+                || noise_val3 > config.dense_forest_forest;
+            let is_forest = noise_val3 > config.forest_threshold;
+
             // Dense Forest
-            if (noise_val > 0.5 || noise_val3 > 0.98) && chance > 0.2 {
-                tiles.insert(Tile::new((x, y), 27, 5));
+            if is_dense && chance > config.dense_forest_roll {
+                tiles.push(Tile::new((x, y), "tree_dense", 0, DrawLayer::Objects));
                 continue;
             }
             // Patch Forest
-            if noise_val3 > 0.5 && noise_val < 0.5 && chance > 0.4 {
+            if is_forest && noise_val < config.dense_forest_elevation && chance > config.forest_roll
+            {
                 let chance2 = rng.gen_range(0.0..1.0);
-                let tile = if chance2 > 0.7 {
-                    rng.gen_range(24..=26)
+                let variant = if chance2 > config.forest_variant_roll {
+                    rng.gen_range(0..=2)
                 } else {
-                    rng.gen_range(24..=25)
+                    rng.gen_range(0..=1)
                 };
-                tiles.insert(Tile::new((x, y), tile, 3));
+                tiles.push(Tile::new((x, y), "tree", variant, DrawLayer::Objects));
                 continue;
             }
             // Sparse Forest
-            if noise_val4 > 0.4 && noise_val < 0.5 && noise_val3 < 0.5 && chance > 0.9 {
+            if noise_val4 > config.sparse_forest_threshold
+                && noise_val < config.dense_forest_elevation
+                && noise_val3 < config.forest_threshold
+                && chance > config.sparse_forest_roll
+            {
                 let chance = rng.gen_range(0.0..1.0);
-                let tile = if chance > 0.78 {
-                    rng.gen_range(28..=29)
+                let tile = if chance > config.sparse_tree_roll {
+                    Tile::new(
+                        (x, y),
+                        "tree_sparse",
+                        rng.gen_range(0..=1),
+                        DrawLayer::Objects,
+                    )
                 } else {
-                    rng.gen_range(24..=25)
+                    Tile::new((x, y), "tree", rng.gen_range(0..=1), DrawLayer::Objects)
                 };
-                tiles.insert(Tile::new((x, y), tile, 3));
+                tiles.push(tile);
                 continue;
             }
 
             // Bones
-            if noise_val > 0.3 && noise_val < 0.5 && noise_val3 < 0.5 && chance > 0.98 {
-                let tile = rng.gen_range(40..=43);
-                tiles.insert(Tile::new((x, y), tile, 1));
+            if in_range(config.bones_elevation)
+                && noise_val3 < config.forest_threshold
+                && chance > config.bones_roll
+            {
+                let variant = rng.gen_range(0..=3);
+                tiles.push(Tile::new((x, y), "bones", variant, DrawLayer::GroundDetail));
                 continue;
             }
 
             // Settlements
-            if noise_val > 0.1 && noise_val < 0.3 && noise_val3 < 0.4 && chance > 0.8 {
+            if in_range(config.settlement_elevation)
+                && noise_val3 < config.settlement_max_forest
+                && chance > config.settlement_roll
+            {
                 let chance2 = rng.gen_range(0.0..1.0);
 
-                if chance2 > 0.98 {
+                if chance2 > config.house_roll {
                     let chance3 = rng.gen_range(0.0..1.0);
-                    let tile = if chance3 > 0.75 {
-                        rng.gen_range(18..=19)
+                    let variant = if chance3 > config.house_variant_roll {
+                        rng.gen_range(2..=3)
                     } else {
-                        rng.gen_range(16..=17)
+                        rng.gen_range(0..=1)
                     };
-                    tiles.insert(Tile::new((x, y), tile, 8));
+                    tiles.push(Tile::new((x, y), "house", variant, DrawLayer::Objects));
                 } else {
-                    if noise_val > 0.2 && noise_val < 0.3 && noise_val3 < 0.3 && chance > 0.9 {
-                        tiles.insert(Tile::new((x, y), 32, 1));
+                    if in_range(config.settlement_detail_elevation)
+                        && noise_val3 < config.settlement_detail_max_forest
+                        && chance > config.settlement_detail_roll
+                    {
+                        tiles.push(Tile::new(
+                            (x, y),
+                            "settlement_detail",
+                            0,
+                            DrawLayer::GroundDetail,
+                        ));
                     }
                 }
 
@@ -294,43 +787,180 @@ fn gen_chunk(gen_seed: u32, start: (i32, i32)) -> (HashSet<Tile>, HashSet<(i32,
 
             // Color Check
             // if noise_val > 0.1 && noise_val4 < 0.5 {
-            //     tiles.insert(Tile::new((x, y), 64, 1));
+            //     tiles.push(Tile::new((x, y), "color_check", 0, DrawLayer::GroundDetail));
             //     continue;
             // }
         }
     }
 
-    (tiles, ground_map)
+    let (ground, ground_tiles) = autotile_ground(ground_map, start, prune_edges);
+    tiles.extend(ground_tiles);
+
+    let mut chunk = ChunkData {
+        pos,
+        tiles,
+        ground,
+        biomes: ChunkGrid::from_cells(start, w, h, biomes),
+        places: Vec::new(),
+    };
+    chunk.places = chunk_places(gen_seed, &chunk, config);
+    chunk
+}
+
+/// Decoration rolls of a chunk, only depends on the seed and chunk so chunks
+/// come out the same however often and in whatever order they are generated
+pub fn chunk_rng(gen_seed: u32, pos: (i32, i32)) -> ChaCha8Rng {
+    let mut key = [0; 32];
+    key[..4].copy_from_slice(&gen_seed.to_le_bytes());
+    key[4..8].copy_from_slice(&pos.0.to_le_bytes());
+    key[8..12].copy_from_slice(&pos.1.to_le_bytes());
+    ChaCha8Rng::from_seed(key)
 }
 
-fn process_tile((x, y): (i32, i32), occupied: &HashSet<(i32, i32)>) -> (i32, usize) {
+/// Picks the ground sprite of every land tile of the chunk starting at `start`,
+/// `ground_map` needs a one tile border around the chunk.
+/// Pruning drops land tiles with a single land neighbour
+pub fn autotile_ground(
+    ground_map: &ChunkGrid<bool>,
+    start: (i32, i32),
+    prune_edges: bool,
+) -> (ChunkGrid<bool>, Vec<Tile>) {
+    let mut tiles = Vec::new();
+    let (w, h) = (ground_map.width() - 2, ground_map.height() - 2);
+    let mut ground = ChunkGrid::new(start, w, h, false);
+    for ((x, y), is_ground) in ground_map.iter() {
+        if !is_ground || !ground.contains((x, y)) {
+            continue;
+        }
+
+        // Ignore edges
+        // This will help in better player visualization when going from land to water
+        let (num_nei, tile) = process_tile((x, y), |pos| ground_map.get(pos) == Some(true));
+        if prune_edges && num_nei == 1 {
+            continue;
+        }
+
+        ground.set((x, y), true);
+        tiles.push(Tile::new((x, y), tile, 0, DrawLayer::Ground));
+    }
+
+    (ground, tiles)
+}
+
+/// Averaged elevation at a grid position along with the individual octaves
+pub fn sample_noise(noise: &Perlin, config: &GenerationConfig, x: i32, y: i32) -> (f64, [f64; 4]) {
+    let octaves = config
+        .octave_scales
+        .map(|s| noise.get([x as f64 / s, y as f64 / s]));
+    (octaves.iter().sum::<f64>() / 4.0, octaves)
+}
+
+/// Same as `sample_noise` with the macro map bias added to the elevation
+pub fn sample_noise_v2(
+    noise: &Perlin,
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+    x: i32,
+    y: i32,
+) -> (f64, [f64; 4]) {
+    let (elevation, octaves) = sample_noise(noise, config, x, y);
+    (
+        elevation + macro_map.elevation_bias(config, (x, y)),
+        octaves,
+    )
+}
+
+/// Octaves sampled at domain warped positions, macro map bias and coast roughness
+/// added to the elevation
+pub fn sample_noise_v3(
+    noise: &Perlin,
+    config: &GenerationConfig,
+    macro_map: &MacroMap,
+    x: i32,
+    y: i32,
+) -> (f64, [f64; 4]) {
+    let (wx, wy) = warp(noise, &config.warp_layers, x as f64, y as f64);
+    let octaves = config.octave_scales.map(|s| noise.get([wx / s, wy / s]));
+    let elevation = octaves.iter().sum::<f64>() / 4.0 + macro_map.elevation_bias(config, (x, y));
+    let roughness = coast_roughness(config, noise, x, y, elevation);
+    (elevation + roughness, octaves)
+}
+
+/// Samples a `w`×`h` area with rows spread across the compute task pool
+pub fn sample_noise_grid(
+    noise: &Perlin,
+    config: &GenerationConfig,
+    origin: (i32, i32),
+    w: usize,
+    h: usize,
+) -> ChunkGrid<(f64, [f64; 4])> {
+    sample_grid(origin, w, h, |x, y| sample_noise(noise, config, x, y))
+}
+
+/// Calls `sample` for every tile of a `w`×`h` area, rows are spread across the compute task pool
+pub fn sample_grid<T: Copy + Send + 'static>(
+    origin: (i32, i32),
+    w: usize,
+    h: usize,
+    sample: impl Fn(i32, i32) -> T + Sync,
+) -> ChunkGrid<T> {
+    let rows = (0..h as i32).collect::<Vec<_>>();
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let cells = rows
+        .par_splat_map(pool, None, |rows| {
+            let mut cells = Vec::with_capacity(rows.len() * w);
+            for j in rows {
+                for i in 0..w as i32 {
+                    cells.push(sample(origin.0 + i, origin.1 + j));
+                }
+            }
+            cells
+        })
+        .into_iter()
+        .flatten()
+        .collect();
+
+    ChunkGrid::from_cells(origin, w, h, cells)
+}
+
+fn process_tile((x, y): (i32, i32), occupied: impl Fn((i32, i32)) -> bool) -> (i32, &'static str) {
     let nei_options = [(-1, 0), (1, 0), (0, -1), (0, 1)];
     let mut nei = [1, 1, 1, 1];
     let mut nei_count = 4;
     for (idx, (i, j)) in nei_options.iter().enumerate() {
-        if !occupied.contains(&(x + i, y + j)) {
+        if !occupied((x + i, y + j)) {
             nei[idx] = 0;
             nei_count -= 1;
         }
     }
 
     let tile = match nei {
-        [0, 1, 1, 0] => 3,
-        [1, 0, 1, 0] => 4,
-        [0, 1, 0, 1] => 1,
-        [1, 0, 0, 1] => 2,
-        _ => 0,
+        [0, 1, 1, 0] => "ground_bottom_left",
+        [1, 0, 1, 0] => "ground_bottom_right",
+        [0, 1, 0, 1] => "ground_top_left",
+        [1, 0, 0, 1] => "ground_top_right",
+        _ => "ground",
     };
 
     (nei_count, tile)
 }
 
+impl ChunkData {
+    pub fn ground_tiles(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.ground
+            .iter()
+            .filter(|(_, is_ground)| *is_ground)
+            .map(|(pos, _)| pos)
+    }
+}
+
 impl Tile {
-    fn new(pos: (i32, i32), sprite: usize, z_index: i32) -> Self {
+    pub fn new(pos: (i32, i32), name: &'static str, variant: usize, layer: DrawLayer) -> Self {
         Self {
             pos,
-            sprite,
-            z_index,
+            name,
+            variant,
+            layer,
         }
     }
 }
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use rand::Rng;
+
+use crate::draw_order::{DrawLayer, YSorted};
+use crate::generation::ActiveGenerationConfig;
+use crate::settings::WorldSettings;
+use crate::terrain::{
+    chunk_rng, ChunkData, ChunkSpawnedEvent, ChunkUnloadedEvent, GenerationSeed, Tile,
+};
+use crate::tileset::TilesetAtlas;
+use crate::utils::*;
+use crate::*;
+
+/// Cycles the frames of tiles marked as animated in the tileset
+pub struct TileAnimationPlugin;
+
+/// Shared clock every animated tile reads its frame from
+#[derive(Resource)]
+pub struct TileAnimationClock {
+    timer: Timer,
+    pub tick: usize,
+}
+
+#[derive(Component)]
+pub struct AnimatedTile {
+    pub chunk: (i32, i32),
+    pub name: &'static str,
+}
+
+/// Animated sprite that isn't part of the generated world, shimmer on open water
+/// and smoke over houses, lives as long as its chunk is spawned
+#[derive(Component)]
+pub struct AmbientSprite;
+
+/// Ambient sprites of every spawned chunk, along with the chunk data they were placed for
+#[derive(Resource, Default)]
+struct AmbientSprites(HashMap<(i32, i32), (Arc<ChunkData>, Vec<Entity>)>);
+
+// Ambient sprites are placed apart from the decorations of the same chunk
+const AMBIENT_SEED_SALT: u32 = 0x27d4_eb2f;
+
+impl Plugin for TileAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileAnimationClock>()
+            .init_resource::<AmbientSprites>()
+            .add_systems(Update, (update_ambient_sprites, animate_tiles));
+    }
+}
+
+impl FromWorld for TileAnimationClock {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource_or_insert_with(WorldSettings::default);
+        Self {
+            timer: Timer::from_seconds(settings.tile_animation_interval, TimerMode::Repeating),
+            tick: 0,
+        }
+    }
+}
+
+impl AnimatedTile {
+    pub fn new(chunk: (i32, i32), tile: &Tile) -> Self {
+        Self {
+            chunk,
+            name: tile.name,
+        }
+    }
+}
+
+/// Spawns the ambient sprites of chunks whose tiles are all spawned
+/// and despawns them with their chunk
+fn update_ambient_sprites(
+    mut commands: Commands,
+    seed: Res<GenerationSeed>,
+    gen_config: Res<ActiveGenerationConfig>,
+    tileset: Res<TilesetAtlas>,
+    mut ambient: ResMut<AmbientSprites>,
+    mut ev_spawned: EventReader<ChunkSpawnedEvent>,
+    mut ev_unloaded: EventReader<ChunkUnloadedEvent>,
+) {
+    // A regenerated chunk is unloaded before its replacement is spawned
+    for ev in ev_unloaded.read() {
+        let Some((data, _)) = ambient.0.get(&ev.pos) else {
+            continue;
+        };
+        if !Arc::ptr_eq(data, &ev.data) {
+            continue;
+        }
+        let (_, entities) = ambient.0.remove(&ev.pos).unwrap();
+        for e in entities {
+            commands.entity(e).despawn();
+        }
+    }
+
+    for ev in ev_spawned.read() {
+        if let Some((_, entities)) = ambient.0.remove(&ev.pos) {
+            for e in entities {
+                commands.entity(e).despawn();
+            }
+        }
+
+        let entities = ambient_tiles(seed.0, gen_config.config.sea_level, &ev.data)
+            .iter()
+            .map(|t| {
+                let (x, y) = grid_to_world(t.pos.0 as f32, t.pos.1 as f32);
+                let (x, y) = center_to_top_left(x, y);
+                let color = match t.name {
+                    // Light glints over whatever color the water is
+                    "water_shimmer" => Color::rgba(1.0, 1.0, 1.0, 0.4),
+                    _ => Color::WHITE,
+                };
+                let mut e = commands.spawn((
+                    SpriteSheetBundle {
+                        texture_atlas: tileset.atlas.clone(),
+                        sprite: TextureAtlasSprite {
+                            color,
+                            ..tileset.sprite(t.name, t.variant)
+                        },
+                        transform: Transform::from_scale(Vec3::splat(SPRITE_SCALE_FACTOR as f32))
+                            .with_translation(vec3(x, y, t.layer.z())),
+                        ..default()
+                    },
+                    AnimatedTile::new(ev.pos, t),
+                    AmbientSprite,
+                ));
+                if t.layer.is_y_sorted() {
+                    e.insert(YSorted(t.layer));
+                }
+                e.id()
+            })
+            .collect();
+        ambient.0.insert(ev.pos, (ev.data.clone(), entities));
+    }
+}
+
+/// Shimmer on water tiles with nothing drawn on them, smoke one tile above houses.
+/// Placement and starting frames are fixed per chunk so revisits look the same
+fn ambient_tiles(gen_seed: u32, sea_level: f64, chunk: &ChunkData) -> Vec<Tile> {
+    let mut rng = chunk_rng(gen_seed ^ AMBIENT_SEED_SALT, chunk.pos);
+    let occupied = chunk.tiles.iter().map(|t| t.pos).collect::<HashSet<_>>();
+
+    let mut tiles = Vec::new();
+    for (pos, biome) in chunk.biomes.iter() {
+        let shimmer = rng.gen_bool(WATER_SHIMMER_CHANCE);
+        if shimmer && (biome.elevation as f64) < sea_level && !occupied.contains(&pos) {
+            tiles.push(Tile {
+                pos,
+                name: "water_shimmer",
+                variant: rng.gen_range(0..4),
+                layer: DrawLayer::GroundDetail,
+            });
+        }
+    }
+    for house in chunk.tiles.iter().filter(|t| t.name == "house") {
+        if rng.gen_bool(CHIMNEY_SMOKE_CHANCE) {
+            tiles.push(Tile {
+                pos: (house.pos.0, house.pos.1 - 1),
+                name: "chimney_smoke",
+                variant: rng.gen_range(0..3),
+                layer: DrawLayer::Objects,
+            });
+        }
+    }
+    tiles
+}
+
+fn animate_tiles(
+    time: Res<Time>,
+    settings: Res<WorldSettings>,
+    tileset: Res<TilesetAtlas>,
+    mut clock: ResMut<TileAnimationClock>,
+    cam_query: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut tile_query: Query<(&AnimatedTile, &mut TextureAtlasSprite)>,
+) {
+    clock.timer.tick(time.delta());
+    if !clock.timer.just_finished() {
+        return;
+    }
+    clock.tick += clock.timer.times_finished_this_tick() as usize;
+
+    // Off screen chunks keep their last frame
+    let mut visible = HashSet::new();
+    for (camera, transform, projection) in cam_query.iter() {
+        if !camera.is_active {
+            continue;
+        }
+
+        let center = transform.translation().truncate();
+        let ((x1, y1), (x2, y2)) =
+            visible_terrain_chunks(center, projection.area, settings.chunk_size);
+        for i in x1..=x2 {
+            for j in y1..=y2 {
+                visible.insert((i, j));
+            }
+        }
+    }
+
+    for (tile, mut sprite) in tile_query.iter_mut() {
+        if !visible.contains(&tile.chunk) {
+            continue;
+        }
+        let Some(def) = tileset.tile(tile.name) else {
+            continue;
+        };
+        if def.frames.is_empty() {
+            continue;
+        }
+
+        // Tiles of a chunk stay in sync, neighbouring chunks are offset so the world doesn't pulse
+        let (cx, cy) = tile.chunk;
+        let offset = cx
+            .wrapping_mul(7)
+            .wrapping_add(cy.wrapping_mul(13))
+            .unsigned_abs() as usize;
+        let frame = (clock.tick + offset) % def.frames.len();
+        sprite.index = def.frames[frame];
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use crate::ron_loader::RonLoader;
+use crate::*;
+
+/// Loads the tileset definition and keeps the shared sprite sheet atlas in sync with it,
+/// every sprite user clones `TilesetAtlas::atlas`
+pub struct TilesetPlugin;
+
+/// Sprite sheet layout and named tiles, loaded from `*.tileset.ron` files
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct Tileset {
+    /// Path of the sprite sheet, relative to the assets folder
+    pub image: String,
+    pub tile_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    #[serde(default)]
+    pub padding: Vec2,
+    #[serde(default)]
+    pub offset: Vec2,
+    pub tiles: HashMap<String, TileDef>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileDef {
+    /// Sprite variants, the generator picks one per tile
+    pub indices: Vec<usize>,
+    /// Animation frames cycled on the shared tile animation clock, empty for static tiles
+    #[serde(default)]
+    pub frames: Vec<usize>,
+}
+
+/// The atlas handle never changes, loading a tileset replaces the atlas behind it
+#[derive(Resource)]
+pub struct TilesetAtlas {
+    /// Tileset file in use, replace to swap themes at runtime
+    pub path: String,
+    pub atlas: Handle<TextureAtlas>,
+    handle: Handle<Tileset>,
+    tileset: Option<Tileset>,
+}
+
+/// Sent whenever a tileset finished loading, sprites spawned before it have stale indices
+#[derive(Event)]
+pub struct TilesetChangedEvent;
+
+impl Plugin for TilesetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Tileset>()
+            .register_asset_loader(RonLoader::<Tileset>::new(&["tileset.ron"]))
+            .init_resource::<TilesetAtlas>()
+            .add_event::<TilesetChangedEvent>()
+            .add_systems(PreUpdate, (swap_tileset, rebuild_atlas).chain());
+    }
+}
+
+impl FromWorld for TilesetAtlas {
+    fn from_world(world: &mut World) -> Self {
+        let handle = world.resource::<AssetServer>().load(TILESET_PATH);
+        let atlas = world
+            .resource::<Assets<TextureAtlas>>()
+            .get_handle_provider()
+            .reserve_handle()
+            .typed();
+
+        Self {
+            path: TILESET_PATH.to_string(),
+            atlas,
+            handle,
+            tileset: None,
+        }
+    }
+}
+
+fn swap_tileset(asset_server: Res<AssetServer>, mut tileset: ResMut<TilesetAtlas>) {
+    let path = asset_server.get_path(tileset.handle.id());
+    if path.is_some_and(|path| path.path().to_str() == Some(tileset.path.as_str())) {
+        return;
+    }
+
+    tileset.handle = asset_server.load(tileset.path.clone());
+}
+
+fn rebuild_atlas(
+    asset_server: Res<AssetServer>,
+    tilesets: Res<Assets<Tileset>>,
+    mut tileset: ResMut<TilesetAtlas>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut ev_asset: EventReader<AssetEvent<Tileset>>,
+    mut ev_changed: EventWriter<TilesetChangedEvent>,
+) {
+    // Edits to the sheet's image are picked up by the asset watcher on their own,
+    // the definition changing needs a new atlas
+    let id = tileset.handle.id();
+    let updated = ev_asset.read().any(|ev| match ev {
+        AssetEvent::LoadedWithDependencies { id: ev_id } | AssetEvent::Modified { id: ev_id } => {
+            *ev_id == id
+        }
+        _ => false,
+    });
+    if !updated {
+        return;
+    }
+    let Some(def) = tilesets.get(id) else {
+        return;
+    };
+
+    let atlas = TextureAtlas::from_grid(
+        asset_server.load(&def.image),
+        def.tile_size,
+        def.columns,
+        def.rows,
+        Some(def.padding),
+        Some(def.offset),
+    );
+    texture_atlases.insert(&tileset.atlas, atlas);
+    tileset.tileset = Some(def.clone());
+    ev_changed.send(TilesetChangedEvent);
+}
+
+impl TilesetAtlas {
+    pub fn is_loaded(&self) -> bool {
+        self.tileset.is_some()
+    }
+
+    pub fn tile(&self, name: &str) -> Option<&TileDef> {
+        self.tileset.as_ref()?.tiles.get(name)
+    }
+
+    pub fn is_animated(&self, name: &str) -> bool {
+        self.tile(name).is_some_and(|tile| !tile.frames.is_empty())
+    }
+
+    /// Sheet index of a tile variant, unknown tiles fall back to the first sprite
+    pub fn index(&self, name: &str, variant: usize) -> usize {
+        match self.tile(name) {
+            Some(tile) if !tile.indices.is_empty() => tile.indices[variant % tile.indices.len()],
+            _ => 0,
+        }
+    }
+
+    /// Sprite sized to a grid cell, so higher resolution sheets render at the same size
+    pub fn sprite(&self, name: &str, variant: usize) -> TextureAtlasSprite {
+        TextureAtlasSprite {
+            index: self.index(name, variant),
+            custom_size: Some(Vec2::new(TILE_W as f32, TILE_H as f32)),
+            ..default()
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use bevy::math::{Rect, Vec2};
+
 use crate::*;
 
 pub fn grid_to_world(x: f32, y: f32) -> (f32, f32) {
@@ -26,12 +28,49 @@ pub fn center_to_top_left(x: f32, y: f32) -> (f32, f32) {
     (x_center, y_center)
 }
 
-pub fn grid_to_chunk(x: f32, y: f32) -> (i32, i32) {
-    let (x, y) = (x / CHUNK_W as f32, y / CHUNK_H as f32);
+/// World position at the middle of a terrain grid tile, terrain grid uses top left coords
+pub fn top_left_grid_to_world(x: i32, y: i32) -> (f32, f32) {
+    let x = x as f32 - GRID_COLS as f32 / 2.0;
+    let y = GRID_ROWS as f32 / 2.0 - y as f32;
+    grid_to_world(x + 0.5, y + 0.5)
+}
+
+pub fn grid_to_chunk(x: f32, y: f32, (w, h): (usize, usize)) -> (i32, i32) {
+    let (x, y) = (x / w as f32, y / h as f32);
     (x.floor() as i32, y.floor() as i32)
 }
 
-pub fn world_to_chunk(x: f32, y: f32) -> (i32, i32) {
+pub fn world_to_chunk(x: f32, y: f32, chunk_size: (usize, usize)) -> (i32, i32) {
+    let (x, y) = world_to_grid(x, y);
+    grid_to_chunk(x, y, chunk_size)
+}
+
+/// Chunk of the terrain under a world position, terrain grid uses top left coords
+pub fn world_to_terrain_chunk(x: f32, y: f32, chunk_size: (usize, usize)) -> (i32, i32) {
     let (x, y) = world_to_grid(x, y);
-    grid_to_chunk(x, y)
+    let (x, y) = center_to_top_left_grid(x, y);
+    grid_to_chunk(x, y, chunk_size)
+}
+
+/// World position of the center of a terrain chunk
+pub fn terrain_chunk_center(x: i32, y: i32, (w, h): (usize, usize)) -> (f32, f32) {
+    let (gx, gy) = ((x * w as i32) as f32, (y * h as i32) as f32);
+    let (x1, y1) = grid_to_world(gx, gy);
+    let (x1, y1) = center_to_top_left(x1, y1);
+    let (x2, y2) = grid_to_world(gx + w as f32 - 1.0, gy + h as f32 - 1.0);
+    let (x2, y2) = center_to_top_left(x2, y2);
+    ((x1 + x2) / 2.0, (y1 + y2) / 2.0)
+}
+
+/// Top left and bottom right terrain chunks of a camera's visible area
+pub fn visible_terrain_chunks(
+    center: Vec2,
+    area: Rect,
+    chunk_size: (usize, usize),
+) -> ((i32, i32), (i32, i32)) {
+    let (min, max) = (center + area.min, center + area.max);
+    (
+        world_to_terrain_chunk(min.x, max.y, chunk_size),
+        world_to_terrain_chunk(max.x, min.y, chunk_size),
+    )
 }
@@ -0,0 +1,71 @@
+use island_procgen::erosion::erosion_grid;
+use island_procgen::generation::GenerationConfig;
+use island_procgen::generator::Generator;
+use island_procgen::macro_map::MacroMap;
+use island_procgen::terrain::ChunkData;
+
+const SEEDS: [u32; 2] = [42, 123_456_789];
+const CHUNK_SIZE: (usize, usize) = (64, 64);
+
+/// Every chunk gets a macro map of its own, so nothing eroded for one chunk is reused by another
+fn generate(gen_seed: u32, pos: (i32, i32), size: (usize, usize)) -> ChunkData {
+    let config = GenerationConfig::default();
+    let macro_map = MacroMap::new(gen_seed, &config);
+    (Generator::latest().gen_chunk)(gen_seed, pos, size, &config, &macro_map)
+}
+
+/// Ground and elevation of `part` match the same tiles of `whole`
+fn assert_same_terrain(part: &ChunkData, whole: &ChunkData) {
+    for ((x, y), is_ground) in part.ground.iter() {
+        assert_eq!(
+            whole.ground.get((x, y)),
+            Some(is_ground),
+            "ground of chunk {:?} differs at {:?}",
+            part.pos,
+            (x, y)
+        );
+    }
+    for ((x, y), biome) in part.biomes.iter() {
+        let other = whole.biomes.get((x, y)).expect("tile is in the whole area");
+        assert_eq!(
+            (biome.elevation, biome.forest),
+            (other.elevation, other.forest),
+            "elevation of chunk {:?} differs at {:?}",
+            part.pos,
+            (x, y)
+        );
+    }
+}
+
+#[test]
+fn region_edge_is_eroded() {
+    // The chunks below meet on a region edge, inside its blend band
+    let config = GenerationConfig::default();
+    let (rw, rh) = config.erosion_region_tiles;
+    assert_eq!((rw * 2 % CHUNK_SIZE.0, rh * 2 % CHUNK_SIZE.1), (0, 0));
+
+    let macro_map = MacroMap::new(SEEDS[0], &config);
+    let edge = 2 * rw as i32;
+    let band = erosion_grid(SEEDS[0], &config, &macro_map, (edge - 16, 0), 32, 64);
+    assert!(band.iter().any(|(_, delta)| delta != 0.0));
+}
+
+#[test]
+fn neighbours_match_across_a_region_edge() {
+    let (w, h) = CHUNK_SIZE;
+    for seed in SEEDS {
+        // Chunks (3, 0) and (4, 0) meet at x = 256, the area of both is the middle of (1, 0)
+        let left = generate(seed, (3, 0), (w, h));
+        let right = generate(seed, (4, 0), (w, h));
+        let whole = generate(seed, (1, 0), (w * 3, h));
+        assert_same_terrain(&left, &whole);
+        assert_same_terrain(&right, &whole);
+
+        // Chunks (0, 3) and (0, 4) meet at y = 256
+        let top = generate(seed, (0, 3), (w, h));
+        let bottom = generate(seed, (0, 4), (w, h));
+        let whole = generate(seed, (0, 1), (w, h * 3));
+        assert_same_terrain(&top, &whole);
+        assert_same_terrain(&bottom, &whole);
+    }
+}
@@ -0,0 +1,96 @@
+use std::mem::size_of;
+
+use island_procgen::chunk_cache::ChunkCache;
+use island_procgen::chunk_grid::ChunkGrid;
+use island_procgen::draw_order::DrawLayer;
+use island_procgen::names::{Place, PlaceKind};
+use island_procgen::terrain::{BiomeSample, ChunkData, Tile};
+
+const CHUNK_SIZE: (usize, usize) = (4, 4);
+
+/// Chunk with a tree on each of its first `trees` tiles
+fn chunk(pos: (i32, i32), trees: usize) -> ChunkData {
+    let (w, h) = CHUNK_SIZE;
+    let origin = (pos.0 * w as i32, pos.1 * h as i32);
+    let tiles = (0..trees)
+        .map(|i| Tile {
+            pos: (origin.0 + (i % w) as i32, origin.1 + (i / w) as i32),
+            name: "tree",
+            variant: 0,
+            layer: DrawLayer::Objects,
+        })
+        .collect();
+
+    ChunkData {
+        pos,
+        tiles,
+        ground: ChunkGrid::new(origin, w, h, true),
+        biomes: ChunkGrid::new(origin, w, h, BiomeSample::default()),
+        places: Vec::new(),
+    }
+}
+
+#[test]
+fn size_grows_with_tiles_and_places() {
+    let empty = chunk((0, 0), 0).size_bytes();
+    let trees = chunk((0, 0), 10);
+    assert_eq!(
+        trees.size_bytes() - empty,
+        trees.tiles.capacity() * size_of::<Tile>()
+    );
+
+    let mut named = chunk((0, 0), 0);
+    named.places.push(Place {
+        kind: PlaceKind::Village,
+        anchor: (0, 0),
+        center: Default::default(),
+        size: 1,
+        name: "Brackenford".to_string(),
+    });
+    let name_len = named.places[0].name.capacity();
+    assert_eq!(named.size_bytes() - empty, size_of::<Place>() + name_len);
+}
+
+#[test]
+fn evicts_least_recently_used_within_budget() {
+    let chunk_bytes = chunk((0, 0), 8).size_bytes();
+    let mut cache = ChunkCache::new(3 * chunk_bytes);
+    for x in 0..3 {
+        cache.insert(chunk((x, 0), 8));
+    }
+    assert_eq!(cache.len(), 3);
+    assert_eq!(cache.used_bytes(), 3 * chunk_bytes);
+
+    // Reading the oldest chunk makes (1, 0) the least recently used one
+    assert!(cache.get((0, 0)).is_some());
+    cache.insert(chunk((3, 0), 8));
+    assert_eq!(cache.len(), 3);
+    assert!(cache.used_bytes() <= 3 * chunk_bytes);
+    assert!(cache.contains((0, 0)));
+    assert!(!cache.contains((1, 0)));
+    assert!(cache.contains((2, 0)) && cache.contains((3, 0)));
+
+    // `contains` doesn't count as a use, (2, 0) goes next
+    cache.insert(chunk((4, 0), 8));
+    assert!(!cache.contains((2, 0)));
+    assert!(cache.contains((0, 0)));
+}
+
+#[test]
+fn replacing_a_chunk_keeps_the_count_right() {
+    let small = chunk((0, 0), 0).size_bytes();
+    let mut cache = ChunkCache::new(usize::MAX);
+    cache.insert(chunk((0, 0), 16));
+    cache.insert(chunk((0, 0), 0));
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.used_bytes(), small);
+}
+
+#[test]
+fn chunk_over_budget_is_still_cached() {
+    let mut cache = ChunkCache::new(1);
+    cache.insert(chunk((0, 0), 4));
+    cache.insert(chunk((1, 0), 4));
+    assert_eq!(cache.len(), 1);
+    assert!(cache.contains((1, 0)));
+}
@@ -0,0 +1,53 @@
+use island_procgen::draw_order::{DrawLayer, YSortOrigin};
+use island_procgen::utils::*;
+use island_procgen::*;
+
+/// Camera heights near and very far from the world origin, in world units
+const CAMERA_YS: [f32; 5] = [0.0, 123_456.0, -987_654.0, 4.0e6, -4.0e6];
+
+/// World y of every tile row of the chunks loaded around a camera, top to bottom
+fn loaded_rows(camera_y: f32, (w, h): (usize, usize), span: i32) -> Vec<f32> {
+    let (_, cy) = world_to_terrain_chunk(0.0, camera_y, (w, h));
+    (cy - span..=cy + span)
+        .flat_map(|chunk| (0..h as i32).map(move |row| chunk * h as i32 + row))
+        .map(|row| top_left_grid_to_world(0, row).1)
+        .collect()
+}
+
+#[test]
+fn objects_sort_by_row_far_from_the_origin() {
+    for chunk_size in [(CHUNK_W, CHUNK_H), (32, 24)] {
+        for camera_y in CAMERA_YS {
+            let origin = YSortOrigin::around(camera_y, chunk_size, MAX_CAMERA_CHUNK_SPAN);
+            let z = loaded_rows(camera_y, chunk_size, MAX_CAMERA_CHUNK_SPAN)
+                .into_iter()
+                .map(|y| origin.z(DrawLayer::Objects, y))
+                .collect::<Vec<_>>();
+
+            // Rows further down are drawn in front of the rows above them
+            for pair in z.windows(2) {
+                assert!(
+                    pair[0] < pair[1],
+                    "rows at z {} and {} aren't sorted for a camera at {camera_y} and chunks of {chunk_size:?}",
+                    pair[0],
+                    pair[1]
+                );
+            }
+            assert!(z[0] > DrawLayer::Objects.z() && z[z.len() - 1] < DrawLayer::Overlay.z());
+        }
+    }
+}
+
+#[test]
+fn flat_layers_ignore_the_origin() {
+    let origin = YSortOrigin::around(4.0e6, (CHUNK_W, CHUNK_H), MAX_CAMERA_CHUNK_SPAN);
+    for layer in [
+        DrawLayer::Lod,
+        DrawLayer::Ground,
+        DrawLayer::GroundDetail,
+        DrawLayer::Overlay,
+    ] {
+        assert_eq!(origin.z(layer, -4.0e6), layer.z());
+        assert_eq!(origin.z(layer, 4.0e6), layer.z());
+    }
+}
@@ -0,0 +1,178 @@
+use island_procgen::draw_order::DrawLayer;
+use island_procgen::generation::GenerationConfig;
+use island_procgen::generator::{Generator, GENERATORS};
+use island_procgen::macro_map::MacroMap;
+use island_procgen::terrain::ChunkData;
+use island_procgen::*;
+
+/// Seeds and chunks every generator version is checked with
+const SEEDS: [u32; 3] = [0, 42, 123_456_789];
+const CHUNKS: [(i32, i32); 3] = [(0, 0), (4, 4), (-3, 7)];
+
+/// Digest of every (seed, chunk) pair above in order, per generator version.
+/// A failing version means the same world code now makes a different world,
+/// released generators must stay as they are, changes go into a new version
+const GOLDEN: &[(u8, [u64; 9])] = &[
+    (
+        1,
+        [
+            0x7650_0e76_edd3_b9e1,
+            0xf9d6_7043_9be6_1195,
+            0x6ab0_449f_0fd7_6cf9,
+            0xe45a_6ffe_749e_5a65,
+            0xfa44_cc1a_53e5_5bb4,
+            0xeda3_cae4_9471_5352,
+            0x9c4a_a53d_e945_f211,
+            0xf8f6_06ca_1355_afd9,
+            0xc979_88b8_2a1f_a738,
+        ],
+    ),
+    (
+        2,
+        [
+            0x290f_8aae_6109_6d88,
+            0x42e7_901b_e467_cf12,
+            0x7d7b_dcc5_43de_36aa,
+            0x0831_050f_b55a_cc7a,
+            0x521c_9232_10ee_fd55,
+            0x73e1_5ccc_4ff5_ae1a,
+            0xc5b4_4f1c_960b_bf39,
+            0xf00f_445d_3aac_a755,
+            0xc317_8324_aae3_93ce,
+        ],
+    ),
+    (
+        3,
+        [
+            0xa9f8_490c_afe2_b094,
+            0xc0ae_bdec_49e0_19ba,
+            0x84a3_469c_0e93_1bdf,
+            0x767f_8c40_b553_c879,
+            0x8975_1dd9_ed53_498a,
+            0xa978_02a6_37df_5193,
+            0x2af6_1694_c4ff_9d6a,
+            0xf416_3c10_807b_1317,
+            0x2b7b_53bc_eff8_c369,
+        ],
+    ),
+    (
+        4,
+        [
+            0x5dea_c350_d7f8_87ca,
+            0x5ba1_352d_c63f_6391,
+            0x7edc_0bf4_f28f_f169,
+            0xcb2d_be3a_978d_5278,
+            0xd10b_1457_0c28_77cd,
+            0x6846_a3ac_a152_9872,
+            0xd95d_dc6c_8daa_6d91,
+            0x9f88_0513_d192_7355,
+            0xd940_2391_408d_835a,
+        ],
+    ),
+];
+
+/// Fixed ids, so reordering or adding layers doesn't change the recorded digests
+fn layer_id(layer: DrawLayer) -> u8 {
+    match layer {
+        DrawLayer::Lod => 0,
+        DrawLayer::Ground => 1,
+        DrawLayer::GroundDetail => 2,
+        DrawLayer::Objects => 3,
+        DrawLayer::Overlay => 4,
+    }
+}
+
+/// FNV-1a over the tiles in generation order and the walkable ground grid
+fn digest(chunk: &ChunkData) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut write = |bytes: &[u8]| {
+        for b in bytes {
+            hash = (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+
+    for t in chunk.tiles.iter() {
+        write(&t.pos.0.to_le_bytes());
+        write(&t.pos.1.to_le_bytes());
+        write(t.name.as_bytes());
+        write(&(t.variant as u64).to_le_bytes());
+        write(&[layer_id(t.layer)]);
+    }
+    for (_, is_ground) in chunk.ground.iter() {
+        write(&[is_ground as u8]);
+    }
+    hash
+}
+
+fn digests(generator: &Generator) -> Vec<u64> {
+    let config = GenerationConfig::default();
+    SEEDS
+        .iter()
+        .flat_map(|seed| CHUNKS.iter().map(move |pos| (*seed, *pos)))
+        .map(|(seed, pos)| {
+            let macro_map = MacroMap::new(seed, &config);
+            digest(&(generator.gen_chunk)(
+                seed,
+                pos,
+                (CHUNK_W, CHUNK_H),
+                &config,
+                &macro_map,
+            ))
+        })
+        .collect()
+}
+
+#[test]
+fn latest_generator_is_registered() {
+    assert_eq!(Generator::latest().version, GENERATOR_VERSION);
+    assert_eq!(
+        GENERATORS.last().map(|g| g.version),
+        Some(GENERATOR_VERSION)
+    );
+}
+
+#[test]
+fn every_generator_has_golden_output() {
+    for generator in GENERATORS {
+        assert!(
+            GOLDEN
+                .iter()
+                .any(|(version, _)| *version == generator.version),
+            "generator v{} has no golden digests",
+            generator.version
+        );
+    }
+}
+
+#[test]
+fn generators_match_golden_output() {
+    for (version, expected) in GOLDEN {
+        let generator = Generator::get(*version).expect("golden version is registered");
+        let actual = digests(generator);
+        assert_eq!(
+            actual, expected,
+            "generator v{version} output changed, got {:#018x?}",
+            actual
+        );
+    }
+}
+
+#[test]
+fn generation_is_deterministic() {
+    let config = GenerationConfig::default();
+    let macro_map = MacroMap::new(42, &config);
+    for generator in GENERATORS {
+        let first = (generator.gen_chunk)(42, (1, 2), (CHUNK_W, CHUNK_H), &config, &macro_map);
+        let second = (generator.gen_chunk)(42, (1, 2), (CHUNK_W, CHUNK_H), &config, &macro_map);
+        assert_eq!(digest(&first), digest(&second));
+    }
+}
+
+/// Golden digests are made with the default config, the game loads the config file
+#[test]
+fn config_file_matches_defaults() {
+    let file = std::fs::read_to_string("assets/generation/default.generation.ron")
+        .expect("default generation config exists");
+    let config: GenerationConfig = ron::de::from_str(&file).expect("config parses");
+    assert_eq!(config.digest(), GenerationConfig::default().digest());
+}
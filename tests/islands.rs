@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use island_procgen::chunk_grid::ChunkGrid;
+use island_procgen::generation::GenerationConfig;
+use island_procgen::islands::{IslandId, Islands};
+use island_procgen::terrain::{BiomeSample, ChunkData};
+
+const CHUNK_SIZE: (usize, usize) = (4, 4);
+
+/// Chunk with walkable ground on the given tiles, positions are terrain grid tiles
+fn chunk(pos: (i32, i32), ground: &[(i32, i32)]) -> Arc<ChunkData> {
+    let (w, h) = CHUNK_SIZE;
+    let origin = (pos.0 * w as i32, pos.1 * h as i32);
+    let mut grid = ChunkGrid::new(origin, w, h, false);
+    for tile in ground {
+        grid.set(*tile, true);
+    }
+
+    Arc::new(ChunkData {
+        pos,
+        tiles: Vec::new(),
+        ground: grid,
+        biomes: ChunkGrid::new(origin, w, h, BiomeSample::default()),
+        places: Vec::new(),
+    })
+}
+
+#[test]
+fn islands_merge_across_chunk_borders() {
+    let config = GenerationConfig::default();
+    // A 4x2 island across the border of chunks (0, 0) and (1, 0), and a single tile
+    // island in the bottom right corner of the right chunk
+    let left = chunk((0, 0), &[(2, 1), (3, 1), (2, 2), (3, 2)]);
+    let right = chunk((1, 0), &[(4, 1), (5, 1), (4, 2), (5, 2), (7, 3)]);
+
+    let mut islands = Islands::default();
+    islands.insert_chunk(left.clone(), &config);
+    islands.rebuild();
+    let part = islands.at((2, 1)).expect("left half is labelled");
+    assert_eq!(part.id, IslandId((2, 1)));
+    assert_eq!(part.area, 4);
+    assert!(!part.complete, "runs into a chunk that isn't loaded");
+
+    islands.insert_chunk(right.clone(), &config);
+    islands.rebuild();
+    let merged = islands.at((5, 2)).expect("right half is labelled");
+    assert_eq!(merged.id, IslandId((2, 1)));
+    assert_eq!(merged.area, 8);
+    assert_eq!((merged.min, merged.max), ((2, 1), (5, 2)));
+    assert!(merged.complete);
+    assert_eq!(islands.at((2, 1)).map(|island| island.id), Some(merged.id));
+
+    let corner = islands.at((7, 3)).expect("corner island is labelled");
+    assert_eq!((corner.id, corner.area), (IslandId((7, 3)), 1));
+    assert!(
+        !corner.complete,
+        "touches unloaded chunks to the right and below"
+    );
+    assert_eq!(islands.iter().count(), 2);
+    assert!(islands.at((0, 0)).is_none());
+
+    islands.remove_chunk(&left);
+    islands.rebuild();
+    let part = islands.at((4, 1)).expect("right half is still labelled");
+    assert_eq!(part.id, IslandId((4, 1)));
+    assert_eq!(part.area, 4);
+    assert!(!part.complete);
+    assert!(islands.at((2, 1)).is_none());
+}
@@ -0,0 +1,101 @@
+use island_procgen::generator::WorldCode;
+use island_procgen::seed::{hash_seed, parse_seed};
+use island_procgen::*;
+
+const WORLDS: [WorldCode; 4] = [
+    WorldCode {
+        version: 1,
+        seed: 0,
+        config: Some(0),
+    },
+    WorldCode {
+        version: 4,
+        seed: 123_456_789,
+        config: Some(0x5a3),
+    },
+    WorldCode {
+        version: 255,
+        seed: u32::MAX,
+        config: Some(CONFIG_DIGEST_MASK),
+    },
+    WorldCode {
+        version: 2,
+        seed: 42,
+        config: None,
+    },
+];
+
+#[test]
+fn codes_round_trip() {
+    for world in WORLDS {
+        let code = world.encode();
+        let expected_len = if world.config.is_some() { 14 } else { 11 };
+        assert_eq!(code.len(), expected_len, "{code} has the wrong length");
+        assert_eq!(
+            WorldCode::decode(&code),
+            Some(world),
+            "{code} didn't round trip"
+        );
+
+        // Typed by hand without separators and in lower case
+        let typed = code.replace('-', "").to_lowercase();
+        assert_eq!(WorldCode::decode(&typed), Some(world));
+    }
+}
+
+#[test]
+fn checksum_rejects_typos() {
+    for world in WORLDS {
+        let code = world.encode();
+        for (i, c) in code.char_indices().filter(|(_, c)| *c != '-') {
+            let typo = if c == 'Z' { 'Y' } else { 'Z' };
+            let mut changed = code.clone();
+            changed.replace_range(i..i + 1, &typo.to_string());
+            assert_eq!(WorldCode::decode(&changed), None, "{changed} was accepted");
+        }
+    }
+
+    // Neither length of code
+    assert_eq!(WorldCode::decode("0G0V-NWQ5-T8R"), None);
+    assert_eq!(WorldCode::decode("0G0V-NWQ5-T8RC-0"), None);
+}
+
+#[test]
+fn legacy_codes_decode_without_config() {
+    let world = WorldCode::decode("010XD-WT5CM").expect("legacy code decodes");
+    assert_eq!(
+        world,
+        WorldCode {
+            version: 1,
+            seed: 123_456_789,
+            config: None,
+        }
+    );
+    assert_eq!(world.encode(), "010XD-WT5CM");
+}
+
+#[test]
+fn parse_seed_tells_numbers_codes_and_text_apart() {
+    assert_eq!(parse_seed("  "), None);
+    assert_eq!(parse_seed("12345"), Some(WorldCode::new(12345)));
+
+    let code = WORLDS[1];
+    assert_eq!(parse_seed(&code.encode()), Some(code));
+    // Terrain falls back to the latest generator, the code keeps the version it asked for
+    let unknown = WORLDS[2];
+    assert_eq!(parse_seed(&unknown.encode()), Some(unknown));
+    assert_eq!(
+        parse_seed("010XD-WT5CM"),
+        Some(WorldCode::decode("010XD-WT5CM").unwrap())
+    );
+
+    // Not a number and no valid code, hashed as a word
+    assert_eq!(
+        parse_seed("island"),
+        Some(WorldCode::new(hash_seed("island")))
+    );
+    assert_eq!(
+        parse_seed("4294967296"),
+        Some(WorldCode::new(hash_seed("4294967296")))
+    );
+}